@@ -1,21 +1,27 @@
 use crate::challenge::{sha_256, Challenge};
 use crate::msg::{InitMsg, QueryAnswer, QueryMsg, ResponseStatus};
 use crate::state::{
-    ActivePoll, Config, ACTIVE_POLLS_KEY, ADMIN_KEY, CONFIG_KEY, CURRENT_CHALLENGE_KEY,
-    DEFAULT_POLL_CONFIG_KEY,
+    ActivePoll, Config, PollRecord, ACTIVE_POLLS_KEY, ADMIN_KEY, CONFIG_KEY, CURRENT_CHALLENGE_KEY,
+    DEFAULT_POLL_CONFIG_KEY, POLL_CONFIG_BOUNDS_KEY, PREFIX_POLLS, PREFIX_STAKE_LOCKS,
 };
 use cosmwasm_std::{
     log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse,
     Querier, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use scrt_finance::errors::ContractError;
+use scrt_finance::events;
+use scrt_finance::lp_staking_msg::LPStakingHandleMsg;
 use scrt_finance::secret_vote_types::PollFactoryHandleMsg::RegisterForUpdates;
 use scrt_finance::secret_vote_types::{
-    InitHook, PollConfig, PollContract, PollFactoryHandleMsg, PollHandleMsg, PollInitMsg,
-    PollMetadata, RevealCommittee,
+    validate_response_block_size, Comparison, InitHook, Outcome, PollConfig, PollConfigBounds,
+    PollContract, PollFactoryHandleMsg, PollHandleMsg, PollInitMsg, PollMember, PollMetadata,
+    RevealCommittee, RevotePolicy, VotingPowerCap, VotingPowerMode, DEFAULT_RESPONSE_BLOCK_SIZE,
 };
 use scrt_finance::types::SecretContract;
-use secret_toolkit::snip20;
+use scrt_finance::voting_power::VotingPowerSource;
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result};
 
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
@@ -25,9 +31,18 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     let owner = env.message.sender;
     TypedStoreMut::attach(&mut deps.storage).store(ADMIN_KEY, &owner)?;
 
+    msg.poll_config_bounds.validate()?;
+    msg.poll_config_bounds.check(&msg.default_poll_config)?;
+    TypedStoreMut::attach(&mut deps.storage)
+        .store(POLL_CONFIG_BOUNDS_KEY, &msg.poll_config_bounds)?;
     TypedStoreMut::attach(&mut deps.storage)
         .store(DEFAULT_POLL_CONFIG_KEY, &msg.default_poll_config)?;
 
+    let response_block_size = msg
+        .response_block_size
+        .unwrap_or(DEFAULT_RESPONSE_BLOCK_SIZE);
+    validate_response_block_size(response_block_size)?;
+
     let prng_seed_hashed = sha_256(&msg.prng_seed.0);
     TypedStoreMut::attach(&mut deps.storage).store(
         CONFIG_KEY,
@@ -41,6 +56,8 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
             prng_seed: prng_seed_hashed,
             min_staked: msg.min_staked.u128(),
             reveal_com: msg.reveal_com,
+            response_block_size,
+            council: msg.council,
         },
     )?;
 
@@ -52,38 +69,104 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: PollFactoryHandleMsg,
 ) -> StdResult<HandleResponse> {
-    match msg {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+
+    let response = match msg {
         PollFactoryHandleMsg::NewPoll {
             poll_metadata,
             poll_config,
             poll_choices,
             pool_viewing_key,
+            start_time,
+            abstain_choice,
+            veto_choice,
+            private_participation,
+            ..
         } => new_poll(
             deps,
             env,
             poll_metadata,
             poll_config.unwrap_or(TypedStore::attach(&deps.storage).load(DEFAULT_POLL_CONFIG_KEY)?),
             poll_choices,
-            pool_viewing_key,
+            Voters::Stakers { pool_viewing_key },
+            start_time,
+            abstain_choice,
+            veto_choice,
+            private_participation.unwrap_or(false),
         ),
-        PollFactoryHandleMsg::UpdateVotingPower { voter, new_power } => {
-            update_voting_power(deps, env, voter, new_power)
-        }
+        PollFactoryHandleMsg::NewMemberPoll {
+            poll_metadata,
+            poll_config,
+            poll_choices,
+            members,
+            start_time,
+            abstain_choice,
+            veto_choice,
+            private_participation,
+            ..
+        } => new_poll(
+            deps,
+            env,
+            poll_metadata,
+            poll_config.unwrap_or(TypedStore::attach(&deps.storage).load(DEFAULT_POLL_CONFIG_KEY)?),
+            poll_choices,
+            Voters::Members(members),
+            start_time,
+            abstain_choice,
+            veto_choice,
+            private_participation.unwrap_or(false),
+        ),
+        PollFactoryHandleMsg::UpdateVotingPower {
+            voter,
+            new_power,
+            staked_since,
+        } => update_voting_power(deps, env, voter, new_power, staked_since),
         PollFactoryHandleMsg::UpdateDefaultPollConfig {
             duration,
             quorum,
             min_threshold,
-        } => update_default_poll_config(deps, env, duration, quorum, min_threshold),
+            quorum_comparison,
+            threshold_comparison,
+            veto_threshold,
+            revote_policy,
+            voting_power_mode,
+            power_cap,
+            lock_stake,
+        } => update_default_poll_config(
+            deps,
+            env,
+            duration,
+            quorum,
+            min_threshold,
+            quorum_comparison,
+            threshold_comparison,
+            veto_threshold,
+            revote_policy,
+            voting_power_mode,
+            power_cap,
+            lock_stake,
+        ),
+        PollFactoryHandleMsg::UpdatePollConfigBounds { bounds } => {
+            update_poll_config_bounds(deps, env, bounds)
+        }
         PollFactoryHandleMsg::RegisterForUpdates {
             challenge,
+            start_time,
             end_time,
-        } => register_for_updates(deps, env, Challenge(challenge), end_time),
+        } => register_for_updates(deps, env, Challenge(challenge), start_time, end_time),
+        PollFactoryHandleMsg::ReportResult {
+            outcome,
+            winning_choice,
+        } => report_result(deps, env, outcome, winning_choice),
+        PollFactoryHandleMsg::LockStake { voter } => lock_stake(deps, env, voter),
         PollFactoryHandleMsg::ChangeAdmin { new_admin } => change_admin(deps, env, new_admin),
         PollFactoryHandleMsg::UpdateConfig {
             new_poll_code,
             new_staking_pool,
             new_min_stake_amount,
             new_reveal_com,
+            new_response_block_size,
+            new_council,
         } => update_config(
             deps,
             env,
@@ -91,52 +174,95 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
             new_staking_pool,
             new_min_stake_amount,
             new_reveal_com,
+            new_response_block_size,
+            new_council,
         ),
-    }
+    };
+
+    pad_handle_result(response, config.response_block_size as usize)
 }
 
 pub fn query<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     msg: QueryMsg,
 ) -> StdResult<Binary> {
-    match msg {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+
+    let response = match msg {
         QueryMsg::ActivePolls { current_time } => query_active_polls(deps, current_time),
         QueryMsg::DefaultPollConfig {} => query_default_poll_config(deps),
+        QueryMsg::PollConfigBounds {} => query_poll_config_bounds(deps),
         QueryMsg::StakingPool {} => query_staking_pool(deps),
         QueryMsg::PollCode {} => query_poll_code(deps),
         QueryMsg::Admin {} => query_admin(deps),
         QueryMsg::RevealCommittee {} => query_reveal_com(deps),
         QueryMsg::MinimumStake {} => query_min_stake(deps),
-    }
+        QueryMsg::Council {} => query_council(deps),
+        QueryMsg::Poll { address } => query_poll(deps, address),
+    };
+
+    pad_query_result(response, config.response_block_size as usize)
 }
 
 // Handle function
 
+/// Who can vote in a new poll
+enum Voters {
+    Stakers { pool_viewing_key: String }, // Of the staking pool. The author needs the minimum stake
+    Members(Vec<PollMember>),             // Only the council can create these polls
+}
+
+#[allow(clippy::too_many_arguments)]
 fn new_poll<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     poll_metadata: PollMetadata,
     poll_config: PollConfig,
     poll_choices: Vec<String>,
-    pool_vk: String,
+    voters: Voters,
+    start_time: Option<u64>,
+    abstain_choice: Option<u8>,
+    veto_choice: Option<u8>,
+    private_participation: bool,
 ) -> StdResult<HandleResponse> {
     let mut config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
 
-    // Verify minimum staked amount
-    let voting_power = snip20::balance_query(
-        &deps.querier,
-        env.message.sender.clone(),
-        pool_vk,
-        256,
-        config.staking_pool.contract_hash.clone(),
-        config.staking_pool.address.clone(),
-    )?;
-    if voting_power.amount.u128() < config.min_staked {
-        return Err(StdError::generic_err(format!(
-            "insufficient staked amount. Minimum staked SEFI to create a poll is {}",
-            config.min_staked / 1_000_000
-        )));
-    }
+    let bounds: PollConfigBounds =
+        TypedStore::attach(&deps.storage).load(POLL_CONFIG_BOUNDS_KEY)?;
+    bounds.check(&poll_config)?;
+    let start_time = start_time.unwrap_or(env.block.time);
+    bounds.check_start_time(start_time, env.block.time)?;
+
+    let voting_power_source = match voters {
+        Voters::Stakers { pool_viewing_key } => {
+            let source = VotingPowerSource::StakingPool {
+                contract: config.staking_pool.clone(),
+            };
+
+            // Verify minimum staked amount
+            let voting_power = source.balance(
+                &deps.querier,
+                env.message.sender.clone(),
+                pool_viewing_key,
+                Some(env.block.time),
+            )?;
+            if voting_power < config.min_staked {
+                return Err(ContractError::InsufficientStake {
+                    min_staked: config.min_staked / 1_000_000,
+                }
+                .into());
+            }
+
+            source
+        }
+        Voters::Members(members) => {
+            if config.council != Some(env.message.sender.clone()) {
+                return Err(StdError::unauthorized());
+            }
+
+            VotingPowerSource::Whitelist { members }
+        }
+    };
 
     let key = Challenge::new(&env, &config.prng_seed);
     TypedStoreMut::attach(&mut deps.storage).store(CURRENT_CHALLENGE_KEY, &key)?;
@@ -152,13 +278,20 @@ fn new_poll<S: Storage, A: Api, Q: Querier>(
         config: poll_config.clone(),
         reveal_com: config.reveal_com.clone(),
         choices: poll_choices,
-        staking_pool: config.staking_pool.clone(),
+        voting_power_source,
+        config_bounds: bounds,
+        start_time: Some(start_time),
+        abstain_choice,
+        veto_choice,
+        private_participation,
+        response_block_size: config.response_block_size,
         init_hook: Some(InitHook {
             contract_addr: env.contract.address,
             code_hash: env.contract_code_hash,
             msg: to_binary(&RegisterForUpdates {
                 challenge: key.to_string(),
-                end_time: env.block.time + poll_config.duration, // If this overflows, we have bigger problems than this :)
+                start_time,
+                end_time: start_time + poll_config.duration, // If this overflows, we have bigger problems than this :)
             })?,
         }),
     };
@@ -180,7 +313,7 @@ fn new_poll<S: Storage, A: Api, Q: Querier>(
             send: vec![],
             label,
         })],
-        log: vec![],
+        log: vec![events::action(events::NEW_POLL)],
         data: None,
     })
 }
@@ -189,11 +322,12 @@ fn register_for_updates<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     response: Challenge,
+    start_time: u64,
     end_time: u64,
 ) -> StdResult<HandleResponse> {
     let challenge: Challenge = TypedStore::attach(&deps.storage).load(CURRENT_CHALLENGE_KEY)?;
     if !response.check_challenge(&challenge.to_hashed()) {
-        return Err(StdError::generic_err("challenge did not match. This function can be called only as a callback from a new poll contract"));
+        return Err(ContractError::ChallengeMismatch.into());
     } else {
         TypedStoreMut::<Challenge, S>::attach(&mut deps.storage).remove(CURRENT_CHALLENGE_KEY);
     }
@@ -206,13 +340,127 @@ fn register_for_updates<S: Storage, A: Api, Q: Querier>(
     active_polls.push(ActivePoll {
         address: env.message.sender.clone(),
         hash: config.poll_contract.code_hash,
+        start_time,
         end_time,
     });
     active_polls_store.store(ACTIVE_POLLS_KEY, &active_polls)?;
 
+    let mut polls = PrefixedStorage::new(PREFIX_POLLS, &mut deps.storage);
+    TypedStoreMut::attach(&mut polls).store(
+        env.message.sender.0.as_bytes(),
+        &PollRecord {
+            start_time,
+            end_time,
+            outcome: None,
+            winning_choice: None,
+        },
+    )?;
+
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![log("new_poll", env.message.sender)],
+        log: vec![
+            events::action(events::REGISTER_POLL),
+            log(events::POLL_ID, env.message.sender),
+        ],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+fn report_result<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    outcome: Outcome,
+    winning_choice: Option<u8>,
+) -> StdResult<HandleResponse> {
+    let poll = env.message.sender;
+
+    // Only polls registered through `RegisterForUpdates` have a record
+    let mut polls = PrefixedStorage::new(PREFIX_POLLS, &mut deps.storage);
+    let mut polls_store = TypedStoreMut::<PollRecord, _>::attach(&mut polls);
+    let mut record = polls_store
+        .may_load(poll.0.as_bytes())?
+        .ok_or_else(StdError::unauthorized)?;
+    if record.outcome.is_some() {
+        return Err(ContractError::ResultAlreadyReported.into());
+    }
+
+    record.outcome = Some(outcome);
+    record.winning_choice = winning_choice;
+    polls_store.store(poll.0.as_bytes(), &record)?;
+
+    // The poll's tally is final, so it shouldn't receive voting power updates anymore
+    let mut active_polls_store = TypedStoreMut::<Vec<ActivePoll>, S>::attach(&mut deps.storage);
+    let mut active_polls = active_polls_store
+        .load(ACTIVE_POLLS_KEY)
+        .unwrap_or_default();
+    active_polls.retain(|p| p.address != poll);
+    active_polls_store.store(ACTIVE_POLLS_KEY, &active_polls)?;
+
+    // Stake locked by the poll's voters can be redeemed again
+    let mut messages = vec![];
+    let locks = ReadonlyPrefixedStorage::new(PREFIX_STAKE_LOCKS, &deps.storage);
+    if TypedStore::<bool, _>::attach(&locks)
+        .may_load(poll.0.as_bytes())?
+        .is_some()
+    {
+        let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+        messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: config.staking_pool.address,
+            callback_code_hash: config.staking_pool.contract_hash,
+            msg: to_binary(&LPStakingHandleMsg::UnlockStake { poll: poll.clone() })?,
+            send: vec![],
+        }));
+    }
+
+    let mut logs = vec![
+        events::action(events::REPORT_RESULT),
+        log(events::POLL_ID, poll),
+    ];
+    logs.extend(events::result(outcome, winning_choice));
+
+    Ok(HandleResponse {
+        messages,
+        log: logs,
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+/// Locks a voter's stake until the poll that asked for it ends
+fn lock_stake<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    voter: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let poll = env.message.sender;
+
+    // Only polls registered through `RegisterForUpdates` have a record
+    let polls = ReadonlyPrefixedStorage::new(PREFIX_POLLS, &deps.storage);
+    let record: PollRecord = TypedStore::attach(&polls)
+        .may_load(poll.0.as_bytes())?
+        .ok_or_else(StdError::unauthorized)?;
+    if record.outcome.is_some() {
+        return Err(ContractError::ResultAlreadyReported.into());
+    }
+
+    let mut locks = PrefixedStorage::new(PREFIX_STAKE_LOCKS, &mut deps.storage);
+    TypedStoreMut::attach(&mut locks).store(poll.0.as_bytes(), &true)?;
+
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    Ok(HandleResponse {
+        messages: vec![CosmosMsg::Wasm(WasmMsg::Execute {
+            contract_addr: config.staking_pool.address,
+            callback_code_hash: config.staking_pool.contract_hash,
+            msg: to_binary(&LPStakingHandleMsg::LockStake {
+                staker: voter,
+                poll: poll.clone(),
+                until: record.end_time,
+            })?,
+            send: vec![],
+        })],
+        log: vec![
+            events::action(events::LOCK_STAKE),
+            log(events::POLL_ID, poll),
+        ],
         data: Some(to_binary(&ResponseStatus::Success)?),
     })
 }
@@ -222,6 +470,7 @@ fn update_voting_power<S: Storage, A: Api, Q: Querier>(
     env: Env,
     voter: HumanAddr,
     new_power: Uint128,
+    staked_since: Option<u64>,
 ) -> StdResult<HandleResponse> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
     if env.message.sender != config.staking_pool.address {
@@ -229,13 +478,19 @@ fn update_voting_power<S: Storage, A: Api, Q: Querier>(
     }
 
     let update_msg = to_binary(&PollHandleMsg::UpdateVotingPower {
-        voter: voter.clone(),
+        voter,
         new_power,
+        staked_since,
     })?; // This API should be kept if a new poll contract is introduced
 
     let mut messages = vec![];
     let active_polls = remove_inactive_polls(deps, &env)?;
     for poll in active_polls {
+        // Nobody could have voted yet, so there's nothing to update
+        if poll.start_time > env.block.time {
+            continue;
+        }
+
         messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: poll.address,
             callback_code_hash: poll.hash,
@@ -246,22 +501,30 @@ fn update_voting_power<S: Storage, A: Api, Q: Querier>(
 
     Ok(HandleResponse {
         messages,
-        log: vec![log("voting power update", voter.0)],
+        log: vec![events::action(events::UPDATE_VOTING_POWER)],
         data: None,
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_default_poll_config<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     duration: Option<u64>,
-    quorum: Option<u8>,
-    min_threshold: Option<u8>,
+    quorum: Option<u16>,
+    min_threshold: Option<u16>,
+    quorum_comparison: Option<Comparison>,
+    threshold_comparison: Option<Comparison>,
+    veto_threshold: Option<u16>,
+    revote_policy: Option<RevotePolicy>,
+    voting_power_mode: Option<VotingPowerMode>,
+    power_cap: Option<VotingPowerCap>,
+    lock_stake: Option<bool>,
 ) -> StdResult<HandleResponse> {
     enforce_admin(deps, &env)?;
 
-    let mut poll_config_store = TypedStoreMut::<PollConfig, S>::attach(&mut deps.storage);
-    let mut default_config = poll_config_store.load(DEFAULT_POLL_CONFIG_KEY)?;
+    let mut default_config: PollConfig =
+        TypedStore::attach(&deps.storage).load(DEFAULT_POLL_CONFIG_KEY)?;
 
     if let Some(new_duration) = duration {
         default_config.duration = new_duration;
@@ -275,11 +538,66 @@ fn update_default_poll_config<S: Storage, A: Api, Q: Querier>(
         default_config.min_threshold = new_threshold;
     }
 
-    poll_config_store.store(DEFAULT_POLL_CONFIG_KEY, &default_config)?;
+    if let Some(new_comparison) = quorum_comparison {
+        default_config.quorum_comparison = new_comparison;
+    }
+
+    if let Some(new_comparison) = threshold_comparison {
+        default_config.threshold_comparison = new_comparison;
+    }
+
+    if let Some(new_threshold) = veto_threshold {
+        default_config.veto_threshold = new_threshold;
+    }
+
+    if let Some(new_policy) = revote_policy {
+        default_config.revote_policy = new_policy;
+    }
+
+    if let Some(new_mode) = voting_power_mode {
+        default_config.voting_power_mode = new_mode;
+    }
+
+    if let Some(new_cap) = power_cap {
+        default_config.power_cap = new_cap;
+    }
+
+    if let Some(new_lock_stake) = lock_stake {
+        default_config.lock_stake = new_lock_stake;
+    }
+
+    let bounds: PollConfigBounds =
+        TypedStore::attach(&deps.storage).load(POLL_CONFIG_BOUNDS_KEY)?;
+    bounds.check(&default_config)?;
+
+    TypedStoreMut::attach(&mut deps.storage).store(DEFAULT_POLL_CONFIG_KEY, &default_config)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![events::action(events::UPDATE_DEFAULT_POLL_CONFIG)],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+fn update_poll_config_bounds<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    bounds: PollConfigBounds,
+) -> StdResult<HandleResponse> {
+    enforce_admin(deps, &env)?;
+
+    bounds.validate()?;
+
+    // The default config must always be usable for new polls
+    let default_config: PollConfig =
+        TypedStore::attach(&deps.storage).load(DEFAULT_POLL_CONFIG_KEY)?;
+    bounds.check(&default_config)?;
+
+    TypedStoreMut::attach(&mut deps.storage).store(POLL_CONFIG_BOUNDS_KEY, &bounds)?;
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: vec![events::action(events::UPDATE_POLL_CONFIG_BOUNDS)],
         data: Some(to_binary(&ResponseStatus::Success)?),
     })
 }
@@ -295,11 +613,12 @@ fn change_admin<S: Storage, A: Api, Q: Querier>(
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: vec![events::action(events::CHANGE_ADMIN)],
         data: Some(to_binary(&ResponseStatus::Success)?),
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn update_config<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
@@ -307,6 +626,8 @@ fn update_config<S: Storage, A: Api, Q: Querier>(
     new_staking_pool: Option<SecretContract>,
     new_min_stake_amount: Option<Uint128>,
     new_reveal_com: Option<RevealCommittee>,
+    new_response_block_size: Option<u16>,
+    new_council: Option<HumanAddr>,
 ) -> StdResult<HandleResponse> {
     enforce_admin(deps, &env)?;
 
@@ -329,11 +650,20 @@ fn update_config<S: Storage, A: Api, Q: Querier>(
         config.reveal_com = new_committee;
     }
 
+    if let Some(new_block_size) = new_response_block_size {
+        validate_response_block_size(new_block_size)?;
+        config.response_block_size = new_block_size;
+    }
+
+    if let Some(new_council) = new_council {
+        config.council = Some(new_council);
+    }
+
     config_store.store(CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
         messages: vec![],
-        log: vec![],
+        log: vec![events::action(events::UPDATE_CONFIG)],
         data: Some(to_binary(&ResponseStatus::Success)?),
     })
 }
@@ -360,6 +690,15 @@ fn query_default_poll_config<S: Storage, A: Api, Q: Querier>(
     })?)
 }
 
+fn query_poll_config_bounds<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<Binary> {
+    let bounds: PollConfigBounds =
+        TypedStore::attach(&deps.storage).load(POLL_CONFIG_BOUNDS_KEY)?;
+
+    Ok(to_binary(&QueryAnswer::PollConfigBounds { bounds })?)
+}
+
 fn query_staking_pool<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
     let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
 
@@ -398,6 +737,27 @@ fn query_min_stake<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> St
     })?)
 }
 
+fn query_council<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+
+    Ok(to_binary(&QueryAnswer::Council {
+        address: config.council,
+    })?)
+}
+
+fn query_poll<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+) -> StdResult<Binary> {
+    let polls = ReadonlyPrefixedStorage::new(PREFIX_POLLS, &deps.storage);
+    let poll: Option<PollRecord> = TypedStore::attach(&polls).may_load(address.0.as_bytes())?;
+
+    match poll {
+        Some(poll) => Ok(to_binary(&QueryAnswer::Poll { poll })?),
+        None => Err(ContractError::UnknownPoll { address }.into()),
+    }
+}
+
 // Helper functions
 
 fn remove_inactive_polls<S: Storage, A: Api, Q: Querier>(
@@ -443,9 +803,425 @@ fn enforce_admin<S: Storage, A: Api, Q: Querier>(
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{coins, from_binary, StdError};
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{from_binary, LogAttribute, StdError};
+
+    /// Log attributes are padded along with the rest of the response
+    fn unpadded_log(res: &HandleResponse) -> Vec<LogAttribute> {
+        res.log
+            .iter()
+            .map(|attr| log(attr.key.trim_end(), attr.value.trim_end()))
+            .collect()
+    }
+
+    fn init_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("admin", &[]);
+
+        let init_msg = InitMsg {
+            prng_seed: Binary::from(b"seed".to_vec()),
+            poll_contract: PollContract {
+                code_id: 1,
+                code_hash: "poll hash".to_string(),
+            },
+            staking_pool: SecretContract {
+                address: HumanAddr("staking pool".to_string()),
+                contract_hash: "pool hash".to_string(),
+            },
+            default_poll_config: PollConfig {
+                duration: 1000,
+                quorum: 3300,
+                min_threshold: 5000,
+                quorum_comparison: Comparison::Inclusive,
+                threshold_comparison: Comparison::Exclusive,
+                veto_threshold: 3340,
+                revote_policy: RevotePolicy::Allowed,
+                voting_power_mode: VotingPowerMode::Balance,
+                power_cap: VotingPowerCap::Uncapped,
+                lock_stake: false,
+            },
+            poll_config_bounds: PollConfigBounds {
+                min_duration: 100,
+                max_duration: 100_000,
+                min_quorum: 1000,
+                max_quorum: 5000,
+                min_threshold: 0,
+                max_threshold: 10_000,
+                min_veto_threshold: 1000,
+                max_veto_threshold: 5000,
+                max_start_delay: 604_800,
+                max_power_cap: VotingPowerCap::Uncapped,
+            },
+            min_staked: Uint128(1_000_000),
+            reveal_com: RevealCommittee {
+                n: 1,
+                revealers: vec![HumanAddr("rev1".to_string())],
+            },
+            response_block_size: None,
+            council: Some(HumanAddr("council".to_string())),
+        };
+        init(&mut deps, env, init_msg).unwrap();
+
+        deps
+    }
+
+    fn register_poll(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, poll: &str) {
+        TypedStoreMut::attach(&mut deps.storage)
+            .store(CURRENT_CHALLENGE_KEY, &Challenge("challenge".to_string()))
+            .unwrap();
+
+        handle(
+            deps,
+            mock_env(poll, &[]),
+            PollFactoryHandleMsg::RegisterForUpdates {
+                challenge: "challenge".to_string(),
+                start_time: 0,
+                end_time: u64::MAX,
+            },
+        )
+        .unwrap();
+    }
 
     #[test]
-    fn test() {}
+    fn test_new_member_poll() {
+        let mut deps = init_helper();
+        let msg = || PollFactoryHandleMsg::NewMemberPoll {
+            poll_metadata: PollMetadata {
+                title: "Hire a designer".to_string(),
+                description: "Should the working group hire a designer?".to_string(),
+                vote_type: "Working group".to_string(),
+                author_addr: None,
+                author_alias: "council".to_string(),
+            },
+            poll_config: None,
+            poll_choices: vec!["Yes".to_string(), "No".to_string()],
+            members: vec![PollMember {
+                address: HumanAddr("member".to_string()),
+                weight: None,
+            }],
+            start_time: None,
+            abstain_choice: None,
+            veto_choice: None,
+            private_participation: None,
+            padding: None,
+        };
+
+        let err = handle(&mut deps, mock_env("alice", &[]), msg()).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        // No stake is needed, so the default querier is enough
+        let res = handle(&mut deps, mock_env("council", &[]), msg()).unwrap();
+        let init_msg: PollInitMsg = match &res.messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { msg, .. }) => from_binary(msg).unwrap(),
+            _ => panic!("unexpected message"),
+        };
+        assert_eq!(
+            init_msg.voting_power_source,
+            VotingPowerSource::Whitelist {
+                members: vec![PollMember {
+                    address: HumanAddr("member".to_string()),
+                    weight: None,
+                }]
+            }
+        );
+
+        let res: QueryAnswer = from_binary(&query(&deps, QueryMsg::Council {}).unwrap()).unwrap();
+        match res {
+            QueryAnswer::Council { address } => {
+                assert_eq!(address, Some(HumanAddr("council".to_string())))
+            }
+            _ => panic!("unexpected query answer"),
+        }
+    }
+
+    #[test]
+    fn test_report_result() {
+        let mut deps = init_helper();
+        register_poll(&mut deps, "poll");
+
+        let report = PollFactoryHandleMsg::ReportResult {
+            outcome: Outcome::Passed,
+            winning_choice: Some(1),
+        };
+        let err = handle(&mut deps, mock_env("impostor", &[]), report).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        let report = PollFactoryHandleMsg::ReportResult {
+            outcome: Outcome::Passed,
+            winning_choice: Some(1),
+        };
+        let res = handle(&mut deps, mock_env("poll", &[]), report).unwrap();
+        assert_eq!(
+            unpadded_log(&res),
+            vec![
+                log("action", "report_result"),
+                log("poll_id", "poll"),
+                log("outcome", "passed"),
+                log("winning_choice", "1"),
+            ]
+        );
+
+        let res: QueryAnswer = from_binary(
+            &query(
+                &deps,
+                QueryMsg::Poll {
+                    address: HumanAddr("poll".to_string()),
+                },
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        match res {
+            QueryAnswer::Poll { poll } => {
+                assert_eq!(poll.outcome, Some(Outcome::Passed));
+                assert_eq!(poll.winning_choice, Some(1));
+            }
+            _ => panic!("unexpected query answer"),
+        }
+
+        // The poll no longer receives voting power updates
+        let res: QueryAnswer =
+            from_binary(&query(&deps, QueryMsg::ActivePolls { current_time: 0 }).unwrap()).unwrap();
+        match res {
+            QueryAnswer::ActivePolls { active_polls } => assert!(active_polls.is_empty()),
+            _ => panic!("unexpected query answer"),
+        }
+
+        let report = PollFactoryHandleMsg::ReportResult {
+            outcome: Outcome::Rejected,
+            winning_choice: None,
+        };
+        let err = handle(&mut deps, mock_env("poll", &[]), report).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::ResultAlreadyReported));
+    }
+
+    #[test]
+    fn test_lock_stake() {
+        let mut deps = init_helper();
+        register_poll(&mut deps, "poll");
+        register_poll(&mut deps, "other poll");
+
+        let lock = || PollFactoryHandleMsg::LockStake {
+            voter: HumanAddr("alice".to_string()),
+        };
+        let err = handle(&mut deps, mock_env("impostor", &[]), lock()).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        let res = handle(&mut deps, mock_env("poll", &[]), lock()).unwrap();
+        let pool_msg = |msg: &LPStakingHandleMsg| {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr("staking pool".to_string()),
+                callback_code_hash: "pool hash".to_string(),
+                msg: to_binary(msg).unwrap(),
+                send: vec![],
+            })
+        };
+        assert_eq!(
+            res.messages,
+            vec![pool_msg(&LPStakingHandleMsg::LockStake {
+                staker: HumanAddr("alice".to_string()),
+                poll: HumanAddr("poll".to_string()),
+                until: u64::MAX,
+            })]
+        );
+
+        // Only polls that locked stake unlock it when they're done
+        let report = || PollFactoryHandleMsg::ReportResult {
+            outcome: Outcome::Passed,
+            winning_choice: Some(0),
+        };
+        let res = handle(&mut deps, mock_env("other poll", &[]), report()).unwrap();
+        assert!(res.messages.is_empty());
+        let res = handle(&mut deps, mock_env("poll", &[]), report()).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![pool_msg(&LPStakingHandleMsg::UnlockStake {
+                poll: HumanAddr("poll".to_string()),
+            })]
+        );
+
+        let err = handle(&mut deps, mock_env("poll", &[]), lock()).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::ResultAlreadyReported));
+    }
+
+    #[test]
+    fn test_update_voting_power() {
+        let mut deps = init_helper();
+        register_poll(&mut deps, "poll");
+
+        let update = PollFactoryHandleMsg::UpdateVotingPower {
+            voter: HumanAddr("alice".to_string()),
+            new_power: Uint128(100),
+            staked_since: None,
+        };
+        let err = handle(&mut deps, mock_env("alice", &[]), update).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        let update = PollFactoryHandleMsg::UpdateVotingPower {
+            voter: HumanAddr("alice".to_string()),
+            new_power: Uint128(100),
+            staked_since: None,
+        };
+        let res = handle(&mut deps, mock_env("staking pool", &[]), update).unwrap();
+        assert_eq!(res.messages.len(), 1);
+        // The staker's address isn't logged
+        assert_eq!(
+            unpadded_log(&res),
+            vec![log("action", "update_voting_power")]
+        );
+    }
+
+    #[test]
+    fn test_response_padding() {
+        let mut deps = init_helper();
+
+        let res = query(&deps, QueryMsg::Admin {}).unwrap();
+        assert_eq!(res.0.len(), 256);
+
+        let update = |block_size: u16| PollFactoryHandleMsg::UpdateConfig {
+            new_poll_code: None,
+            new_staking_pool: None,
+            new_min_stake_amount: None,
+            new_reveal_com: None,
+            new_response_block_size: Some(block_size),
+            new_council: None,
+        };
+        let err = handle(&mut deps, mock_env("admin", &[]), update(0)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::InvalidResponseBlockSize));
+
+        let res = handle(&mut deps, mock_env("admin", &[]), update(64)).unwrap();
+        assert_eq!(res.data.unwrap().0.len(), 256);
+        let res = query(&deps, QueryMsg::Admin {}).unwrap();
+        assert_eq!(res.0.len(), 64);
+    }
+
+    #[test]
+    fn test_default_config_out_of_bounds() {
+        let mut deps = init_helper();
+
+        let err = handle(
+            &mut deps,
+            mock_env("admin", &[]),
+            PollFactoryHandleMsg::UpdateDefaultPollConfig {
+                duration: None,
+                quorum: Some(0),
+                min_threshold: None,
+                quorum_comparison: None,
+                threshold_comparison: None,
+                veto_threshold: None,
+                revote_policy: None,
+                voting_power_mode: None,
+                power_cap: None,
+                lock_stake: None,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::PercentageOutOfBounds {
+                name: "quorum".to_string(),
+                min: 1000,
+                max: 5000,
+                value: 0,
+            })
+        );
+
+        handle(
+            &mut deps,
+            mock_env("admin", &[]),
+            PollFactoryHandleMsg::UpdateDefaultPollConfig {
+                duration: Some(5000),
+                quorum: Some(4000),
+                min_threshold: None,
+                quorum_comparison: None,
+                threshold_comparison: Some(Comparison::Inclusive),
+                veto_threshold: None,
+                revote_policy: None,
+                voting_power_mode: None,
+                power_cap: None,
+                lock_stake: None,
+            },
+        )
+        .unwrap();
+        let res: QueryAnswer =
+            from_binary(&query(&deps, QueryMsg::DefaultPollConfig {}).unwrap()).unwrap();
+        match res {
+            QueryAnswer::DefaultPollConfig { poll_config } => {
+                assert_eq!(poll_config.duration, 5000);
+                assert_eq!(poll_config.quorum, 4000);
+                assert_eq!(poll_config.threshold_comparison, Comparison::Inclusive);
+            }
+            _ => panic!("unexpected query answer"),
+        }
+    }
+
+    #[test]
+    fn test_update_poll_config_bounds() {
+        let mut deps = init_helper();
+        let bounds = PollConfigBounds {
+            min_duration: 2000,
+            max_duration: 100_000,
+            min_quorum: 1000,
+            max_quorum: 5000,
+            min_threshold: 0,
+            max_threshold: 10_000,
+            min_veto_threshold: 1000,
+            max_veto_threshold: 5000,
+            max_start_delay: 604_800,
+            max_power_cap: VotingPowerCap::Uncapped,
+        };
+
+        let err = handle(
+            &mut deps,
+            mock_env("not admin", &[]),
+            PollFactoryHandleMsg::UpdatePollConfigBounds {
+                bounds: bounds.clone(),
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        // The current default config (1000 seconds) would fall outside the new bounds
+        let err = handle(
+            &mut deps,
+            mock_env("admin", &[]),
+            PollFactoryHandleMsg::UpdatePollConfigBounds { bounds },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::DurationOutOfBounds {
+                min: 2000,
+                max: 100_000,
+                duration: 1000,
+            })
+        );
+
+        let inverted = PollConfigBounds {
+            min_duration: 100,
+            max_duration: 100_000,
+            min_quorum: 6000,
+            max_quorum: 5000,
+            min_threshold: 0,
+            max_threshold: 10_000,
+            min_veto_threshold: 1000,
+            max_veto_threshold: 5000,
+            max_start_delay: 604_800,
+            max_power_cap: VotingPowerCap::Uncapped,
+        };
+        let err = handle(
+            &mut deps,
+            mock_env("admin", &[]),
+            PollFactoryHandleMsg::UpdatePollConfigBounds { bounds: inverted },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::InvalidPercentageBounds {
+                name: "quorum".to_string(),
+                min: 6000,
+                max: 5000,
+            })
+        );
+    }
 }
@@ -1,7 +1,9 @@
-use crate::state::ActivePoll;
+use crate::state::{ActivePoll, PollRecord};
 use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
-use scrt_finance::secret_vote_types::{PollConfig, PollContract, RevealCommittee};
+use scrt_finance::secret_vote_types::{
+    PollConfig, PollConfigBounds, PollContract, RevealCommittee,
+};
 use scrt_finance::types::SecretContract;
 use serde::{Deserialize, Serialize};
 
@@ -9,10 +11,13 @@ use serde::{Deserialize, Serialize};
 pub struct InitMsg {
     pub prng_seed: Binary,
     pub poll_contract: PollContract,
-    pub staking_pool: SecretContract,
+    pub staking_pool: SecretContract, // Or anything with its interface, e.g. the vote escrow
     pub default_poll_config: PollConfig,
+    pub poll_config_bounds: PollConfigBounds,
     pub min_staked: Uint128,
     pub reveal_com: RevealCommittee,
+    pub response_block_size: Option<u16>, // Defaults to DEFAULT_RESPONSE_BLOCK_SIZE
+    pub council: Option<HumanAddr>,       // Can create member polls. Nobody can if not set
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -22,11 +27,16 @@ pub enum QueryMsg {
         current_time: u64, /* linux epoch */
     },
     DefaultPollConfig {},
+    PollConfigBounds {},
     StakingPool {},
     PollCode {},
     Admin {},
     RevealCommittee {},
     MinimumStake {},
+    Council {},
+    Poll {
+        address: HumanAddr,
+    },
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
@@ -34,11 +44,14 @@ pub enum QueryMsg {
 pub enum QueryAnswer {
     ActivePolls { active_polls: Vec<ActivePoll> },
     DefaultPollConfig { poll_config: PollConfig },
+    PollConfigBounds { bounds: PollConfigBounds },
     StakingPool { contract: SecretContract },
     PollCode { contract: PollContract },
     Admin { address: HumanAddr },
     RevealCommittee { committee: RevealCommittee },
     MinimumStake { amount: Uint128 },
+    Council { address: Option<HumanAddr> },
+    Poll { poll: PollRecord },
 }
 
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
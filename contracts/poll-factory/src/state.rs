@@ -1,14 +1,17 @@
 use cosmwasm_std::HumanAddr;
 use schemars::JsonSchema;
-use scrt_finance::secret_vote_types::{PollContract, RevealCommittee};
+use scrt_finance::secret_vote_types::{Outcome, PollContract, RevealCommittee};
 use scrt_finance::types::SecretContract;
 use serde::{Deserialize, Serialize};
 
 pub const ADMIN_KEY: &[u8] = b"admin";
 pub const CONFIG_KEY: &[u8] = b"config";
 pub const DEFAULT_POLL_CONFIG_KEY: &[u8] = b"defaultconfig";
+pub const POLL_CONFIG_BOUNDS_KEY: &[u8] = b"pollconfigbounds";
 pub const CURRENT_CHALLENGE_KEY: &[u8] = b"prngseed";
 pub const ACTIVE_POLLS_KEY: &[u8] = b"active_polls";
+pub const PREFIX_POLLS: &[u8] = b"polls";
+pub const PREFIX_STAKE_LOCKS: &[u8] = b"stakelocks"; // Polls that locked stake, to unlock when they report
 
 #[derive(Serialize, Deserialize)]
 pub struct Config {
@@ -18,11 +21,23 @@ pub struct Config {
     pub prng_seed: [u8; 32],
     pub min_staked: u128,
     pub reveal_com: RevealCommittee,
+    pub response_block_size: u16,   // Also passed on to new polls
+    pub council: Option<HumanAddr>, // Can create member polls
 }
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct ActivePoll {
     pub address: HumanAddr,
     pub hash: String,
+    pub start_time: u64,
     pub end_time: u64,
 }
+
+/// Every poll registered by this factory, keyed by address under `PREFIX_POLLS`
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct PollRecord {
+    pub start_time: u64,
+    pub end_time: u64,
+    pub outcome: Option<Outcome>, // Set once the poll reports its result
+    pub winning_choice: Option<u8>,
+}
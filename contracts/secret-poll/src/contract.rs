@@ -1,18 +1,28 @@
-use crate::msg::{FinalizeAnswer, QueryAnswer, QueryMsg, ResponseStatus};
-use crate::querier::query_staking_balance;
+use crate::msg::{FinalizeAnswer, PollPhase, QueryAnswer, QueryMsg, ResponseStatus};
+use crate::querier::query_stake_info;
 use crate::state::{
-    read_vote, store_vote, StoredPollConfig, StoredRevealConfig, Vote, CONFIG_KEY, METADATA_KEY,
-    NUM_OF_VOTERS_KEY, OWNER_KEY, REVEAL_CONFIG, STAKING_POOL_KEY, TALLY_KEY,
+    add_voter, is_member_key_valid, read_revotes, read_uncapped_power, read_vote,
+    read_voting_power_source, require_current_storage_version, set_member_key, store_revotes,
+    store_uncapped_power, store_vote, PollResult, StoredPollConfig, StoredRevealConfig, Vote,
+    CLOSING_TOTAL_KEY, CONFIG_KEY, FACTORY_KEY, METADATA_KEY, NUM_OF_VOTERS_KEY, OWNER_KEY,
+    RESPONSE_BLOCK_SIZE_KEY, RESULT_KEY, REVEAL_CONFIG, STORAGE_VERSION, STORAGE_VERSION_KEY,
+    TALLY_KEY, VOTING_POWER_SOURCE_KEY,
 };
+use crate::tally::{compute_result, TallyResult, TallyRules};
 use cosmwasm_std::{
     log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse,
     Querier, StdError, StdResult, Storage, Uint128, WasmMsg,
 };
-use scrt_finance::secret_vote_types::{PollHandleMsg, PollInitMsg, PollMetadata};
+use scrt_finance::errors::ContractError;
+use scrt_finance::events;
+use scrt_finance::secret_vote_types::{
+    validate_response_block_size, Outcome, PollFactoryHandleMsg, PollHandleMsg, PollInitMsg,
+    PollMetadata, RevotePolicy, VotingPowerCap, VotingPowerMode,
+};
 use scrt_finance::types::SecretContract;
-use secret_toolkit::snip20;
-use secret_toolkit::snip20::{balance_query, Balance};
+use scrt_finance::voting_power::VotingPowerSource;
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result};
 use sha2::{Digest, Sha256};
 use std::mem::size_of;
 
@@ -21,45 +31,76 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: PollInitMsg,
 ) -> StdResult<InitResponse> {
+    TypedStoreMut::attach(&mut deps.storage).store(STORAGE_VERSION_KEY, &STORAGE_VERSION)?;
     let owner = env.message.sender;
     TypedStoreMut::attach(&mut deps.storage).store(OWNER_KEY, &owner)?; // This is in fact the factory contract
-    TypedStoreMut::attach(&mut deps.storage).store(STAKING_POOL_KEY, &msg.staking_pool)?;
+    msg.voting_power_source.check()?;
+    if let VotingPowerMode::StakeAge { .. } = msg.config.voting_power_mode {
+        if msg.voting_power_source.staking_pool().is_none() {
+            return Err(ContractError::StakeAgeWithoutStakingPool.into());
+        }
+    }
+    if msg.config.lock_stake
+        && (msg.voting_power_source.staking_pool().is_none() || msg.init_hook.is_none())
+    {
+        return Err(ContractError::LockStakeUnsupported.into());
+    }
+    TypedStoreMut::attach(&mut deps.storage)
+        .store(VOTING_POWER_SOURCE_KEY, &msg.voting_power_source)?;
 
     if msg.choices.len() < 2 {
-        return Err(StdError::generic_err(
-            "you have to provide at least two choices",
-        ));
+        return Err(ContractError::NotEnoughChoices.into());
+    }
+    validate_special_choice("abstain", msg.abstain_choice, msg.choices.len())?;
+    validate_special_choice("veto", msg.veto_choice, msg.choices.len())?;
+    if msg.veto_choice.is_some() && msg.veto_choice == msg.abstain_choice {
+        return Err(ContractError::AbstainIsVeto.into());
     }
 
     // Sanity checks to prevent starting a new poll by mistake
     if msg.metadata.title.len() < 2 {
-        return Err(StdError::generic_err(
-            "poll title must be at least 2 characters long",
-        ));
+        return Err(ContractError::TitleTooShort.into());
     }
     if msg.metadata.description.len() < 10 {
-        return Err(StdError::generic_err(
-            "poll description must be at least 10 characters long",
-        ));
+        return Err(ContractError::DescriptionTooShort.into());
     }
     if msg.metadata.author_alias.len() < 3 {
-        return Err(StdError::generic_err(
-            "poll author alias must be at least 3 characters long",
-        ));
+        return Err(ContractError::AuthorAliasTooShort.into());
     }
     TypedStoreMut::attach(&mut deps.storage).store(METADATA_KEY, &msg.metadata)?;
 
+    // The factory already enforces these, but the poll shouldn't rely on that
+    msg.config_bounds.validate()?;
+    msg.config_bounds.check(&msg.config)?;
+    validate_response_block_size(msg.response_block_size)?;
+    TypedStoreMut::attach(&mut deps.storage)
+        .store(RESPONSE_BLOCK_SIZE_KEY, &msg.response_block_size)?;
+
     let tally: Vec<u128> = vec![0; msg.choices.len()];
     TypedStoreMut::attach(&mut deps.storage).store(TALLY_KEY, &tally)?;
 
-    let ending = env.block.time + msg.config.duration;
+    let start = msg.start_time.unwrap_or(env.block.time);
+    msg.config_bounds.check_start_time(start, env.block.time)?;
+
+    let ending = start + msg.config.duration;
     TypedStoreMut::attach(&mut deps.storage).store(
         CONFIG_KEY,
         &StoredPollConfig {
+            start_timestamp: start,
             end_timestamp: ending,
             quorum: msg.config.quorum,
             min_threshold: msg.config.min_threshold,
+            quorum_comparison: msg.config.quorum_comparison,
+            threshold_comparison: msg.config.threshold_comparison,
             choices: msg.choices,
+            abstain_choice: msg.abstain_choice,
+            veto_choice: msg.veto_choice,
+            veto_threshold: msg.config.veto_threshold,
+            revote_policy: msg.config.revote_policy,
+            voting_power_mode: msg.config.voting_power_mode,
+            power_cap: msg.config.power_cap,
+            lock_stake: msg.config.lock_stake,
+            private_participation: msg.private_participation,
             finalized: false,
             valid: false,
             rolling_hash: [0u8; 32],
@@ -78,6 +119,14 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
 
     let mut messages = vec![];
     if let Some(init_hook) = msg.init_hook {
+        // The hook registers this poll with the factory, which expects its result when finalized
+        TypedStoreMut::attach(&mut deps.storage).store(
+            FACTORY_KEY,
+            &SecretContract {
+                address: init_hook.contract_addr.clone(),
+                contract_hash: init_hook.code_hash.clone(),
+            },
+        )?;
         messages.push(CosmosMsg::Wasm(WasmMsg::Execute {
             contract_addr: init_hook.contract_addr,
             callback_code_hash: init_hook.code_hash,
@@ -86,9 +135,19 @@ pub fn init<S: Storage, A: Api, Q: Querier>(
         }));
     }
 
+    let phase = if start > env.block.time {
+        PollPhase::Pending
+    } else {
+        PollPhase::Active
+    };
+
     Ok(InitResponse {
         messages,
-        log: vec![],
+        log: vec![
+            events::action(events::INIT),
+            log(events::POLL_ID, env.contract.address),
+            log(events::PHASE, phase_name(phase)),
+        ],
     })
 }
 
@@ -97,34 +156,49 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: PollHandleMsg,
 ) -> StdResult<HandleResponse> {
-    match msg {
+    require_current_storage_version(deps)?;
+    let block_size: u16 = TypedStore::attach(&deps.storage).load(RESPONSE_BLOCK_SIZE_KEY)?;
+
+    let response = match msg {
         PollHandleMsg::Vote {
             choice,
             staking_pool_viewing_key,
             salt,
+            ..
         } => vote(deps, env, choice, staking_pool_viewing_key, salt),
-        PollHandleMsg::UpdateVotingPower { voter, new_power } => {
-            update_voting_power(deps, env, voter, new_power.u128())
-        }
-        PollHandleMsg::Finalize { rolling_hash } => finalize(deps, env, rolling_hash),
-    }
+        PollHandleMsg::UpdateVotingPower {
+            voter,
+            new_power,
+            staked_since,
+        } => update_voting_power(deps, env, voter, new_power.u128(), staked_since),
+        PollHandleMsg::Finalize { rolling_hash, .. } => finalize(deps, env, rolling_hash),
+        PollHandleMsg::Cancel { .. } => cancel(deps, env),
+    };
+
+    pad_handle_result(response, block_size as usize)
 }
 
 pub fn query<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     msg: QueryMsg,
 ) -> StdResult<Binary> {
-    match msg {
+    require_current_storage_version(deps)?;
+    let block_size: u16 = TypedStore::attach(&deps.storage).load(RESPONSE_BLOCK_SIZE_KEY)?;
+
+    let response = match msg {
         QueryMsg::Choices {} => query_choices(deps),
-        QueryMsg::HasVoted { voter } => query_has_voted(deps, voter),
+        QueryMsg::HasVoted { voter, key } => query_has_voted(deps, voter, key),
         QueryMsg::Tally {} => query_tally(deps),
+        QueryMsg::Result {} => query_result(deps),
         QueryMsg::Vote { voter, key } => query_vote(deps, voter, key),
         QueryMsg::NumberOfVoters {} => query_num_of_voters(deps),
-        QueryMsg::VoteInfo {} => query_vote_info(deps),
+        QueryMsg::VoteInfo { current_time } => query_vote_info(deps, current_time),
         QueryMsg::RevealCommittee {} => query_reveal_com(deps),
         QueryMsg::Revealed {} => query_revealed(deps),
         QueryMsg::RollingHash {} => query_rolling_hash(deps),
-    }
+    };
+
+    pad_query_result(response, block_size as usize)
 }
 
 // Handle
@@ -136,22 +210,40 @@ pub fn vote<S: Storage, A: Api, Q: Querier>(
     key: String,
     salt: String,
 ) -> StdResult<HandleResponse> {
-    let mut config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let mut config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
     require_vote_ongoing(&env, &config)?;
 
-    let staking_pool: SecretContract = TypedStore::attach(&deps.storage).load(STAKING_POOL_KEY)?;
-    let voting_power = snip20::balance_query(
-        &deps.querier,
-        env.message.sender.clone(),
-        key,
-        256,
-        staking_pool.contract_hash,
-        staking_pool.address,
-    )?
-    .amount
-    .u128();
+    let source = read_voting_power_source(deps)?;
+    let uncapped_power = match (source.staking_pool(), config.voting_power_mode) {
+        (_, VotingPowerMode::Balance) => {
+            let balance = source.balance(
+                &deps.querier,
+                env.message.sender.clone(),
+                key.clone(),
+                Some(env.block.time),
+            )?;
+            if !source.authenticates() {
+                set_member_key(deps, &env.message.sender, &key)?;
+            }
+            balance
+        }
+        (Some(staking_pool), mode) => {
+            let (balance, staked_since) =
+                query_stake_info(deps, staking_pool, env.message.sender.clone(), key)?;
+            mode.voting_power(balance, Some(staked_since), env.block.time)?
+        }
+        (None, _) => return Err(ContractError::StakeAgeWithoutStakingPool.into()), // Rejected at init
+    };
+    record_total_voting_power(deps, &source, &config)?;
+    let voting_power = cap_voting_power(deps, &env, &config, uncapped_power)?;
 
     let prev_vote = read_vote(deps, &env.message.sender).ok();
+    let mut messages = vec![];
+    if prev_vote.is_some() {
+        check_revote(deps, &env, &config)?;
+    } else if config.lock_stake {
+        messages.push(lock_stake_msg(deps, &env.message.sender)?);
+    }
     update_vote(
         deps,
         &env.message.sender,
@@ -161,6 +253,7 @@ pub fn vote<S: Storage, A: Api, Q: Querier>(
             voting_power,
         },
     )?;
+    store_uncapped_power(deps, &env.message.sender, uncapped_power)?;
 
     let new_hash = roll_hash(
         config.rolling_hash,
@@ -175,8 +268,11 @@ pub fn vote<S: Storage, A: Api, Q: Querier>(
     TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
 
     Ok(HandleResponse {
-        messages: vec![],
-        log: vec![log("voted", env.message.sender.to_string())],
+        messages,
+        log: vec![
+            events::action(events::VOTE),
+            log(events::POLL_ID, env.contract.address),
+        ],
         data: Some(to_binary(&ResponseStatus::Success)?),
     })
 }
@@ -185,18 +281,41 @@ pub fn update_voting_power<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     voter: HumanAddr,
-    new_power: u128,
+    new_balance: u128,
+    staked_since: Option<u64>,
 ) -> StdResult<HandleResponse> {
-    let config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
-    require_vote_ongoing(&env, &config)?;
+    let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
 
     let owner: HumanAddr = TypedStore::attach(&deps.storage).load(OWNER_KEY)?;
     if env.message.sender != owner {
         return Err(StdError::unauthorized());
     }
 
-    let mut logs = vec![];
+    // Logged the same whether or not the staker voted, so it doesn't reveal who did
+    let logs = vec![
+        events::action(events::UPDATE_VOTING_POWER),
+        log(events::POLL_ID, env.contract.address.clone()),
+    ];
+
+    // A cancelled poll's tally is frozen, but failing here would fail the staker's transaction.
+    // Polls with other sources get updates too, but their voting power doesn't depend on stake
+    let source = read_voting_power_source(deps)?;
+    if config.finalized || source.staking_pool().is_none() {
+        return Ok(HandleResponse {
+            messages: vec![],
+            log: logs,
+            data: Some(to_binary(&ResponseStatus::Success)?),
+        });
+    }
+    require_vote_ongoing(&env, &config)?;
+    record_total_voting_power(deps, &source, &config)?;
+
     if let Ok(prev_vote) = read_vote(deps, &voter) {
+        let uncapped_power =
+            config
+                .voting_power_mode
+                .voting_power(new_balance, staked_since, env.block.time)?;
+        let new_power = cap_voting_power(deps, &env, &config, uncapped_power)?;
         update_vote(
             deps,
             &voter,
@@ -206,8 +325,7 @@ pub fn update_voting_power<S: Storage, A: Api, Q: Querier>(
                 voting_power: new_power,
             },
         )?;
-
-        logs.push(log("voting_power_updated", voter.to_string()));
+        store_uncapped_power(deps, &voter, uncapped_power)?;
     }
 
     Ok(HandleResponse {
@@ -223,12 +341,20 @@ pub fn finalize<S: Storage, A: Api, Q: Querier>(
     rolling_hash: String,
 ) -> StdResult<HandleResponse> {
     let mut config: StoredPollConfig = TypedStoreMut::attach(&mut deps.storage).load(CONFIG_KEY)?;
+    if let Some(PollResult {
+        outcome: Outcome::Cancelled,
+        ..
+    }) = TypedStore::attach(&deps.storage).may_load(RESULT_KEY)?
+    {
+        return Err(ContractError::PollCancelled.into());
+    }
+
     if env.block.time < config.end_timestamp {
-        return Err(StdError::generic_err("vote has not ended yet"));
+        return Err(ContractError::VoteNotEnded.into());
     }
 
     if hex::encode(&config.rolling_hash) != rolling_hash {
-        return Err(StdError::generic_err("incorrect rolling hash"));
+        return Err(ContractError::IncorrectRollingHash.into());
     }
 
     let mut reveal_conf_store = TypedStoreMut::attach(&mut deps.storage);
@@ -242,21 +368,31 @@ pub fn finalize<S: Storage, A: Api, Q: Querier>(
     }
 
     if reveal_conf.revealed.contains(&env.message.sender) {
-        return Err(StdError::generic_err("already finalized the vote"));
+        return Err(ContractError::AlreadyRevealed.into());
     }
 
     reveal_conf.revealed.push(env.message.sender);
     reveal_conf.num_revealed += 1;
     reveal_conf_store.store(REVEAL_CONFIG, &reveal_conf)?;
 
+    // Recorded on the first reveal if not before, so it doesn't depend on when the last one comes
+    let total_voting_power = closing_total_voting_power(deps, &config)?;
+
+    let mut logs = vec![
+        events::action(events::FINALIZE),
+        log(events::POLL_ID, env.contract.address),
+        log(events::NUM_REVEALED, reveal_conf.num_revealed),
+    ];
+
     if reveal_conf.num_revealed > reveal_conf.committee.n {
         let tally: Vec<u128> = TypedStore::attach(&deps.storage).load(TALLY_KEY)?; // Already revealed
         return Ok(HandleResponse {
             messages: vec![],
-            log: vec![],
+            log: logs,
             data: Some(to_binary(&FinalizeAnswer {
                 finalized: config.finalized,
                 valid: Some(config.valid),
+                result: TypedStore::attach(&deps.storage).may_load(RESULT_KEY)?,
                 choices: Some(config.choices),
                 tally: Some(tally.iter().map(|c| Uint128(*c)).collect()),
             })?),
@@ -264,10 +400,11 @@ pub fn finalize<S: Storage, A: Api, Q: Querier>(
     } else if reveal_conf.num_revealed < reveal_conf.committee.n {
         return Ok(HandleResponse {
             messages: vec![],
-            log: vec![],
+            log: logs,
             data: Some(to_binary(&FinalizeAnswer {
                 finalized: false,
                 valid: None,
+                result: None,
                 choices: None,
                 tally: None,
             })?),
@@ -279,31 +416,75 @@ pub fn finalize<S: Storage, A: Api, Q: Querier>(
     let tally: Vec<u128> = TypedStore::attach(&deps.storage).load(TALLY_KEY)?;
 
     // Validation tests
-    let sefi_balance = query_staking_balance(deps)?;
-    let total_vote_count: u128 = tally.iter().sum();
-    let participation = 100 * total_vote_count / sefi_balance; // This should give a percentage integer X/100%
-    if participation > config.quorum as u128 {
-        config.valid = true;
-    }
-    if let Some(winning_choice) = tally.iter().max() {
-        config.valid = config.valid && (*winning_choice > config.min_threshold as u128)
-    } else {
-        return Err(StdError::generic_err("storage is corrupted")); // iter().max() returns `None` only when the Vec is empty
-    }
+    let tally_result = compute_result(&tally, total_voting_power, &tally_rules(&config))?;
+    config.valid = tally_result.quorum_met();
+    let result = poll_result(tally_result);
 
     TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+    TypedStoreMut::attach(&mut deps.storage).store(RESULT_KEY, &result)?;
+
+    logs.push(log(events::PHASE, phase_name(PollPhase::Finalized)));
+    logs.extend(events::result(result.outcome, result.winning_choice));
+
     Ok(HandleResponse {
-        messages: vec![],
-        log: vec![],
+        messages: report_result_msg(deps, &result)?.into_iter().collect(),
+        log: logs,
         data: Some(to_binary(&FinalizeAnswer {
             finalized: config.finalized,
             valid: Some(config.valid),
+            result: Some(result),
             choices: Some(config.choices),
             tally: Some(tally.iter().map(|c| Uint128(*c)).collect()),
         })?),
     })
 }
 
+pub fn cancel<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let metadata: PollMetadata = TypedStore::attach(&deps.storage).load(METADATA_KEY)?;
+    if metadata.author_addr != Some(env.message.sender.clone()) {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    if config.finalized {
+        return Err(ContractError::AlreadyFinalized.into());
+    }
+    if config.end_timestamp < env.block.time {
+        return Err(ContractError::CancelAfterEnd.into());
+    }
+
+    config.finalized = true;
+    config.valid = false;
+    TypedStoreMut::attach(&mut deps.storage).store(CONFIG_KEY, &config)?;
+
+    // Nothing is tallied, so a cancelled poll doesn't reveal anything about its ballots
+    let result = PollResult {
+        outcome: Outcome::Cancelled,
+        winning_choice: None,
+        total_voting_power: Uint128(0),
+        participating_supply: Uint128(0),
+        turnout: 0,
+        choice_percentages: None,
+    };
+    TypedStoreMut::attach(&mut deps.storage).store(RESULT_KEY, &result)?;
+
+    let mut logs = vec![
+        events::action(events::CANCEL),
+        log(events::POLL_ID, env.contract.address),
+        log(events::PHASE, phase_name(PollPhase::Finalized)),
+    ];
+    logs.extend(events::result(result.outcome, result.winning_choice));
+
+    Ok(HandleResponse {
+        messages: report_result_msg(deps, &result)?.into_iter().collect(),
+        log: logs,
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
 // Query
 
 pub fn query_choices<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
@@ -315,21 +496,36 @@ pub fn query_choices<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) ->
 
 pub fn query_vote_info<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
+    current_time: Option<u64>,
 ) -> StdResult<Binary> {
     let metadata: PollMetadata = TypedStore::attach(&deps.storage).load(METADATA_KEY)?;
     let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
     let reveal_conf: StoredRevealConfig = TypedStore::attach(&deps.storage).load(REVEAL_CONFIG)?;
+    // Queries don't know the block time, so an open poll's phase depends on the caller's clock
+    let phase = match current_time {
+        Some(time) => Some(poll_phase(&config, time)),
+        None if config.finalized => Some(PollPhase::Finalized),
+        None => None,
+    };
     Ok(to_binary(&QueryAnswer::VoteInfo {
         metadata,
         config,
         reveal_com: reveal_conf.committee,
+        phase,
     })?)
 }
 
 pub fn query_has_voted<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     voter: HumanAddr,
+    key: Option<String>,
 ) -> StdResult<Binary> {
+    let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    if config.private_participation {
+        let key = key.ok_or_else(|| StdError::from(ContractError::ViewingKeyRequired))?;
+        authenticate_voter(deps, &voter, key)?;
+    }
+
     let has_voted = read_vote(deps, &voter).is_ok();
     Ok(to_binary(&QueryAnswer::HasVoted { has_voted })?)
 }
@@ -347,25 +543,30 @@ pub fn query_tally<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> St
     })?)
 }
 
+pub fn query_result<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<Binary> {
+    let result: Option<PollResult> = TypedStore::attach(&deps.storage).may_load(RESULT_KEY)?;
+    let result = result.ok_or_else(|| StdError::from(ContractError::NotFinalized))?;
+
+    let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    Ok(to_binary(&QueryAnswer::Result {
+        choices: config.choices,
+        result,
+    })?)
+}
+
 pub fn query_vote<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     voter: HumanAddr,
     key: String,
 ) -> StdResult<Binary> {
-    let staking_pool: SecretContract = TypedStore::attach(&deps.storage).load(STAKING_POOL_KEY)?;
-    let _balance: Balance = balance_query(
-        &deps.querier,
-        voter.clone(),
-        key,
-        256,
-        staking_pool.contract_hash,
-        staking_pool.address,
-    )?; // Balance doesn't matter, we're just verifying the viewing key
-
-    let vote: Vote = TypedStore::attach(&deps.storage).load(voter.0.as_bytes())?;
+    authenticate_voter(deps, &voter, key)?;
+
+    let vote = read_vote(deps, &voter)?;
+    let uncapped_power = read_uncapped_power(deps, &voter)?.unwrap_or(vote.voting_power);
     Ok(to_binary(&QueryAnswer::Vote {
         choice: vote.choice,
         voting_power: Uint128(vote.voting_power),
+        uncapped_voting_power: Uint128(uncapped_power),
     })?)
 }
 
@@ -423,30 +624,27 @@ fn update_vote<S: Storage, A: Api, Q: Querier>(
     let mut tally: Vec<u128> = TypedStoreMut::attach(&mut deps.storage).load(TALLY_KEY)?;
 
     if let Some(previous_vote) = previous_vote {
-        if let Some(choice_tally) = tally.get_mut(previous_vote.choice as usize) {
-            *choice_tally -= previous_vote.voting_power; // Can't underflow, `choice_tally` >= `old_vote.voting_power`
-        } else {
-            // Shouldn't really happen since user already voted, but just in case
-            return Err(StdError::generic_err(format!(
-                "previous choice {} does not exist in this poll",
-                previous_vote.choice
-            )));
-        }
+        // A missing choice or an underflow shouldn't really happen since the user already voted,
+        // but just in case
+        let choice_tally = tally
+            .get_mut(previous_vote.choice as usize)
+            .ok_or_else(|| StdError::from(ContractError::StorageCorrupted))?;
+        *choice_tally = choice_tally
+            .checked_sub(previous_vote.voting_power)
+            .ok_or_else(|| StdError::from(ContractError::TallyUnderflow))?;
     } else {
-        // If it's a new vote - increment the number of voters
-        let mut voters_store = TypedStoreMut::attach(&mut deps.storage);
-        let num_of_voters: u64 = voters_store.load(NUM_OF_VOTERS_KEY)?;
-        voters_store.store(NUM_OF_VOTERS_KEY, &(num_of_voters + 1))?;
+        // If it's a new vote - index the voter and increment the number of voters
+        add_voter(deps, voter)?;
     }
 
-    if let Some(choice_tally) = tally.get_mut(new_vote.choice as usize) {
-        *choice_tally += new_vote.voting_power; // Can't overflow, `choice_tally` <= `gov_token.total_supply()`
-    } else {
-        return Err(StdError::generic_err(format!(
-            "choice {} does not exist in this poll",
-            new_vote.choice
-        )));
-    }
+    let choice_tally = tally.get_mut(new_vote.choice as usize).ok_or_else(|| {
+        StdError::from(ContractError::InvalidChoice {
+            choice: new_vote.choice,
+        })
+    })?;
+    *choice_tally = choice_tally
+        .checked_add(new_vote.voting_power)
+        .ok_or_else(|| StdError::from(ContractError::TallyOverflow))?;
 
     TypedStoreMut::attach(&mut deps.storage).store(TALLY_KEY, &tally)?;
     store_vote(deps, voter, new_vote.choice, new_vote.voting_power)?; // This also discards the old vote
@@ -454,10 +652,12 @@ fn update_vote<S: Storage, A: Api, Q: Querier>(
     Ok(())
 }
 
-fn roll_hash(hash: [u8; 32], voter: &HumanAddr, vote: Vote, salt: String) -> [u8; 32] {
+/// Off-chain tooling relies on this to reproduce the rolling hash, so changes here are breaking
+pub fn roll_hash(hash: [u8; 32], voter: &HumanAddr, vote: Vote, salt: String) -> [u8; 32] {
     let mut extended = Vec::with_capacity(
         hash.len() + voter.0.len() + size_of::<u8>() + size_of::<u128>() + salt.len(),
     );
+    extended.extend_from_slice(&hash);
     extended.extend_from_slice(voter.0.as_bytes());
     extended.extend_from_slice(&vote.choice.to_le_bytes());
     extended.extend_from_slice(&vote.voting_power.to_le_bytes());
@@ -466,19 +666,223 @@ fn roll_hash(hash: [u8; 32], voter: &HumanAddr, vote: Vote, salt: String) -> [u8
     Sha256::digest(&extended).into()
 }
 
-fn require_vote_ongoing(env: &Env, config: &StoredPollConfig) -> StdResult<()> {
-    if config.end_timestamp < env.block.time {
-        return Err(StdError::generic_err("vote has ended"));
+fn poll_phase(config: &StoredPollConfig, current_time: u64) -> PollPhase {
+    if config.finalized {
+        PollPhase::Finalized
+    } else if current_time < config.start_timestamp {
+        PollPhase::Pending
+    } else if current_time <= config.end_timestamp {
+        PollPhase::Active
+    } else {
+        PollPhase::Ended
+    }
+}
+
+fn phase_name(phase: PollPhase) -> &'static str {
+    match phase {
+        PollPhase::Pending => "pending",
+        PollPhase::Active => "active",
+        PollPhase::Ended => "ended",
+        PollPhase::Finalized => "finalized",
+    }
+}
+
+fn tally_rules(config: &StoredPollConfig) -> TallyRules {
+    TallyRules {
+        quorum: config.quorum,
+        min_threshold: config.min_threshold,
+        quorum_comparison: config.quorum_comparison,
+        threshold_comparison: config.threshold_comparison,
+        abstain_choice: config.abstain_choice,
+        veto_choice: config.veto_choice,
+        veto_threshold: config.veto_threshold,
+    }
+}
+
+fn poll_result(tally_result: TallyResult) -> PollResult {
+    let quorum_met = tally_result.quorum_met();
+    PollResult {
+        outcome: tally_result.outcome,
+        winning_choice: tally_result.winning_choice,
+        total_voting_power: Uint128(tally_result.total_votes),
+        participating_supply: Uint128(tally_result.total_staked),
+        turnout: tally_result.participation,
+        // Same anonymity set reasoning as in `query_tally`
+        choice_percentages: if quorum_met {
+            Some(tally_result.percentages)
+        } else {
+            None
+        },
+    }
+}
+
+/// Reports the result to the factory that created this poll, if any
+fn report_result_msg<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    result: &PollResult,
+) -> StdResult<Option<CosmosMsg>> {
+    let factory: Option<SecretContract> =
+        TypedStore::attach(&deps.storage).may_load(FACTORY_KEY)?;
+
+    factory
+        .map(|factory| {
+            Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: factory.address,
+                callback_code_hash: factory.contract_hash,
+                msg: to_binary(&PollFactoryHandleMsg::ReportResult {
+                    outcome: result.outcome,
+                    winning_choice: result.winning_choice,
+                })?,
+                send: vec![],
+            }))
+        })
+        .transpose()
+}
+
+/// Asks the factory to lock the voter's stake until the poll ends, so it can't be redeemed and
+/// staked again from another address to vote twice
+fn lock_stake_msg<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    voter: &HumanAddr,
+) -> StdResult<CosmosMsg> {
+    let factory: SecretContract = TypedStore::attach(&deps.storage).load(FACTORY_KEY)?;
+
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: factory.address,
+        callback_code_hash: factory.contract_hash,
+        msg: to_binary(&PollFactoryHandleMsg::LockStake {
+            voter: voter.clone(),
+        })?,
+        send: vec![],
+    }))
+}
+
+fn validate_special_choice(name: &str, choice: Option<u8>, num_of_choices: usize) -> StdResult<()> {
+    if let Some(choice) = choice {
+        if choice as usize >= num_of_choices {
+            return Err(ContractError::InvalidSpecialChoice {
+                name: name.to_string(),
+                choice,
+            }
+            .into());
+        }
+    }
+
+    Ok(())
+}
+
+/// The quorum's denominator, e.g. the total staked, as of `as_of` where it decays with time
+fn total_voting_power<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    as_of: u64,
+) -> StdResult<u128> {
+    read_voting_power_source(deps)?.total_supply(&deps.querier, Some(as_of))
+}
+
+/// Staking pools push every stake change while the poll is active. The total is recorded as of the
+/// poll's end, which for a pool whose power decays (i.e. the vote escrow) is what it will have
+/// decayed to unless stakes change again before then, and they'd be pushed. The total recorded
+/// last is then the total when the poll closed
+fn record_total_voting_power<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    source: &VotingPowerSource,
+    config: &StoredPollConfig,
+) -> StdResult<()> {
+    if source.staking_pool().is_some() {
+        let total = source.total_supply(&deps.querier, Some(config.end_timestamp))?;
+        TypedStoreMut::attach(&mut deps.storage).store(CLOSING_TOTAL_KEY, &total)?;
+    }
+
+    Ok(())
+}
+
+/// The quorum's denominator. Sources that don't push updates, and pools that had no stake changes
+/// or votes, are read as of the poll's end on the first call after it closes
+fn closing_total_voting_power<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    config: &StoredPollConfig,
+) -> StdResult<u128> {
+    let recorded: Option<u128> = TypedStore::attach(&deps.storage).may_load(CLOSING_TOTAL_KEY)?;
+    if let Some(total) = recorded {
+        return Ok(total);
+    }
+
+    let total = total_voting_power(deps, config.end_timestamp)?;
+    TypedStoreMut::attach(&mut deps.storage).store(CLOSING_TOTAL_KEY, &total)?;
+    Ok(total)
+}
+
+/// A share-of-total cap is measured against the total staked right now, so ballots that aren't
+/// updated keep the cap they were cast or last updated with
+fn cap_voting_power<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    config: &StoredPollConfig,
+    voting_power: u128,
+) -> StdResult<u128> {
+    let total_staked = match config.power_cap {
+        VotingPowerCap::ShareOfTotal { .. } => total_voting_power(deps, env.block.time)?,
+        _ => 0, // Not needed
+    };
+
+    config.power_cap.apply(voting_power, total_staked)
+}
+
+/// Enforces the poll's re-vote policy on a voter who already voted, counting the re-vote
+fn check_revote<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: &Env,
+    config: &StoredPollConfig,
+) -> StdResult<()> {
+    match config.revote_policy {
+        RevotePolicy::Allowed => Ok(()),
+        RevotePolicy::Disallowed => Err(ContractError::RevoteNotAllowed.into()),
+        RevotePolicy::Limited { max_revotes } => {
+            let revotes = read_revotes(deps, &env.message.sender)?;
+            if revotes >= max_revotes {
+                return Err(ContractError::RevoteLimitReached { max_revotes }.into());
+            }
+            store_revotes(deps, &env.message.sender, revotes + 1)
+        }
+        RevotePolicy::UntilLock { lock_period } => {
+            if env.block.time >= config.end_timestamp.saturating_sub(lock_period) {
+                return Err(ContractError::BallotsLocked.into());
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Verifies the viewing key belongs to the voter
+fn authenticate_voter<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    voter: &HumanAddr,
+    key: String,
+) -> StdResult<()> {
+    let source = read_voting_power_source(deps)?;
+    if source.authenticates() {
+        // Balance doesn't matter, we're just verifying the viewing key
+        source.balance(&deps.querier, voter.clone(), key, None)?;
+    } else if !is_member_key_valid(deps, voter, &key)? {
+        return Err(StdError::unauthorized());
     }
 
     Ok(())
 }
 
+fn require_vote_ongoing(env: &Env, config: &StoredPollConfig) -> StdResult<()> {
+    match poll_phase(config, env.block.time) {
+        PollPhase::Active => Ok(()),
+        PollPhase::Pending => Err(ContractError::VoteNotStarted.into()),
+        PollPhase::Ended | PollPhase::Finalized => Err(ContractError::VoteEnded.into()),
+    }
+}
+
 fn require_vote_finalized_and_valid(config: &StoredPollConfig) -> StdResult<()> {
     if !config.finalized {
-        return Err(StdError::generic_err("vote hasn't been finalized yet"));
+        return Err(ContractError::NotFinalized.into());
     } else if !config.valid {
-        return Err(StdError::generic_err("vote hasn't passed quorum"));
+        return Err(ContractError::QuorumNotPassed.into());
     }
 
     Ok(())
@@ -487,11 +891,18 @@ fn require_vote_finalized_and_valid(config: &StoredPollConfig) -> StdResult<()>
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::state::read_voter;
     use cosmwasm_std::testing::{
         mock_dependencies, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR,
     };
-    use cosmwasm_std::{coins, from_binary, BlockInfo, Coin, ContractInfo, MessageInfo, StdError};
-    use scrt_finance::secret_vote_types::{PollConfig, RevealCommittee};
+    use cosmwasm_std::{
+        coins, from_binary, from_slice, BlockInfo, Coin, ContractInfo, Empty, MessageInfo,
+        QuerierResult, QueryRequest, ReadonlyStorage, StdError, WasmQuery,
+    };
+    use scrt_finance::lp_staking_msg::{LPStakingQueryAnswer, LPStakingQueryMsg};
+    use scrt_finance::secret_vote_types::{
+        Comparison, InitHook, PollConfig, PollConfigBounds, PollMember, RevealCommittee,
+    };
 
     pub fn mock_env<U: Into<HumanAddr>>(sender: U, sent: &[Coin], block: u64, time: u64) -> Env {
         Env {
@@ -512,14 +923,23 @@ mod tests {
         }
     }
 
-    fn init_helper() -> (
-        StdResult<InitResponse>,
-        Extern<MockStorage, MockApi, MockQuerier>,
-    ) {
-        let mut deps = mock_dependencies(20, &[]);
-        let env = mock_env("factory", &[], 0, 0);
+    fn mock_config_bounds() -> PollConfigBounds {
+        PollConfigBounds {
+            min_duration: 100,
+            max_duration: 100_000,
+            min_quorum: 1000,
+            max_quorum: 5000,
+            min_threshold: 0,
+            max_threshold: 10_000,
+            min_veto_threshold: 1000,
+            max_veto_threshold: 5000,
+            max_start_delay: 604_800,
+            max_power_cap: VotingPowerCap::Uncapped,
+        }
+    }
 
-        let init_msg = PollInitMsg {
+    fn mock_init_msg() -> PollInitMsg {
+        PollInitMsg {
             metadata: PollMetadata {
                 title: "test vote".to_string(),
                 description: "hey hey this is a test vote".to_string(),
@@ -529,81 +949,123 @@ mod tests {
             },
             config: PollConfig {
                 duration: 1000,
-                quorum: 33,
+                quorum: 3300,
                 min_threshold: 0,
+                quorum_comparison: Comparison::Inclusive,
+                threshold_comparison: Comparison::Exclusive,
+                veto_threshold: 3340,
+                revote_policy: RevotePolicy::Allowed,
+                voting_power_mode: VotingPowerMode::Balance,
+                power_cap: VotingPowerCap::Uncapped,
+                lock_stake: false,
             },
             reveal_com: RevealCommittee {
                 n: 2,
                 revealers: vec![HumanAddr("rev1".into()), HumanAddr("rev2".into())],
             },
             choices: vec!["Yes".into(), "No".into()],
-            staking_pool: SecretContract {
-                address: HumanAddr("staking pool".to_string()),
-                contract_hash: "".to_string(),
+            voting_power_source: VotingPowerSource::StakingPool {
+                contract: SecretContract {
+                    address: HumanAddr("staking pool".to_string()),
+                    contract_hash: "".to_string(),
+                },
             },
+            config_bounds: mock_config_bounds(),
+            start_time: None,
+            abstain_choice: None,
+            veto_choice: None,
+            private_participation: false,
+            response_block_size: 256,
             init_hook: None,
+        }
+    }
+
+    fn init_helper() -> (
+        StdResult<InitResponse>,
+        Extern<MockStorage, MockApi, MockQuerier>,
+    ) {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+
+        (init(&mut deps, env, mock_init_msg()), deps)
+    }
+
+    /// Answers every query as the staking pool's `TotalLocked`
+    struct StakingPoolQuerier {
+        total_locked: u128,
+    }
+
+    impl Querier for StakingPoolQuerier {
+        fn raw_query(&self, _request: &[u8]) -> QuerierResult {
+            Ok(to_binary(&LPStakingQueryAnswer::TotalLocked {
+                amount: Uint128(self.total_locked),
+            }))
+        }
+    }
+
+    fn init_with_total_staked(
+        total_locked: u128,
+    ) -> Extern<MockStorage, MockApi, StakingPoolQuerier> {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: StakingPoolQuerier { total_locked },
         };
+        init(&mut deps, mock_env("factory", &[], 0, 0), mock_init_msg()).unwrap();
+
+        deps
+    }
 
-        (init(&mut deps, env, init_msg), deps)
+    fn finalize_by_committee<S: Storage, A: Api, Q: Querier>(
+        deps: &mut Extern<S, A, Q>,
+        time: u64,
+    ) -> FinalizeAnswer {
+        let hash = hex::encode([0u8; 32]); // Votes are added directly, so the hash never rolls
+
+        let res = finalize(deps, mock_env("rev1", &[], 1, time), hash.clone()).unwrap();
+        let answer: FinalizeAnswer = from_binary(&res.data.unwrap()).unwrap();
+        assert!(!answer.finalized);
+
+        let res = finalize(deps, mock_env("rev2", &[], 1, time), hash).unwrap();
+        from_binary(&res.data.unwrap()).unwrap()
     }
 
     #[test]
     fn test_vote_info() {
         let mut deps = mock_dependencies(20, &[]);
-        let env = mock_env("factory", &[], 0, 0);
-        let init_msg = PollInitMsg {
-            metadata: PollMetadata {
-                title: "test_vote_info".to_string(),
-                description: "test_vote_info".to_string(),
-                vote_type: "cool type".to_string(),
-                author_addr: Some(HumanAddr("proposer".to_string())),
-                author_alias: "proposer".into(),
-            },
-            config: PollConfig {
-                duration: 1000,
-                quorum: 33,
-                min_threshold: 0,
-            },
-            reveal_com: RevealCommittee {
-                n: 2,
-                revealers: vec![HumanAddr("rev1".into()), HumanAddr("rev2".into())],
-            },
-            choices: vec!["Yes".into(), "No".into()],
-            staking_pool: SecretContract {
-                address: HumanAddr("staking pool".to_string()),
-                contract_hash: "".to_string(),
-            },
-            init_hook: None,
-        };
-        init(&mut deps, env, init_msg).unwrap();
+        let mut init_msg = mock_init_msg();
+        init_msg.metadata.title = "test_vote_info".to_string();
+        init_msg.start_time = Some(100);
+        init(&mut deps, mock_env("factory", &[], 0, 0), init_msg).unwrap();
 
-        let res = query_vote_info(&deps).unwrap();
-        assert_eq!(
-            res,
-            to_binary(&QueryAnswer::VoteInfo {
-                metadata: PollMetadata {
-                    title: "test_vote_info".to_string(),
-                    description: "test_vote_info".to_string(),
-                    vote_type: "cool type".to_string(),
-                    author_addr: Some(HumanAddr("proposer".to_string())),
-                    author_alias: "proposer".into(),
-                },
-                config: StoredPollConfig {
-                    end_timestamp: 1000,
-                    quorum: 33,
-                    min_threshold: 0,
-                    choices: vec!["Yes".into(), "No".into()],
-                    finalized: false,
-                    valid: false,
-                    rolling_hash: [0u8; 32]
-                },
-                reveal_com: RevealCommittee {
-                    n: 2,
-                    revealers: vec![HumanAddr("rev1".into()), HumanAddr("rev2".into())],
-                }
-            })
-            .unwrap()
+        let vote_info = |current_time: Option<u64>| match from_binary(
+            &query_vote_info(&deps, current_time).unwrap(),
         )
+        .unwrap()
+        {
+            QueryAnswer::VoteInfo {
+                metadata,
+                config,
+                reveal_com,
+                phase,
+            } => (metadata, config, reveal_com, phase),
+            _ => panic!("unexpected answer"),
+        };
+
+        let (metadata, config, reveal_com, phase) = vote_info(Some(500));
+        assert_eq!(metadata.title, "test_vote_info");
+        assert_eq!(config.start_timestamp, 100);
+        assert_eq!(config.end_timestamp, 1100);
+        assert_eq!(config.choices, vec!["Yes".to_string(), "No".to_string()]);
+        assert_eq!(reveal_com.n, 2);
+        assert_eq!(phase, Some(PollPhase::Active));
+
+        let (_, _, _, phase) = vote_info(Some(50));
+        assert_eq!(phase, Some(PollPhase::Pending));
+
+        // Without a time, an open poll's phase is unknown
+        let (_, _, _, phase) = vote_info(None);
+        assert_eq!(phase, None);
     }
 
     #[test]
@@ -623,7 +1085,7 @@ mod tests {
         .unwrap();
 
         let err = query_tally(&deps).unwrap_err();
-        assert_eq!(err, StdError::generic_err("vote hasn't been finalized yet"));
+        assert_eq!(err, StdError::from(ContractError::NotFinalized));
 
         // Finalize
         let mut config: StoredPollConfig = TypedStoreMut::attach(&mut deps.storage)
@@ -647,29 +1109,1395 @@ mod tests {
     }
 
     #[test]
-    fn test_tally_before_ended() {}
-
-    #[test]
-    fn test_tally_below_quorum() {}
-
-    #[test]
-    fn test_minimum_deposit() {}
+    fn test_init_config_out_of_bounds() {
+        let with_config = |duration: u64, quorum: u16, min_threshold: u16| PollConfig {
+            duration,
+            quorum,
+            min_threshold,
+            quorum_comparison: Comparison::Inclusive,
+            threshold_comparison: Comparison::Exclusive,
+            veto_threshold: 3340,
+            revote_policy: RevotePolicy::Allowed,
+            voting_power_mode: VotingPowerMode::Balance,
+            power_cap: VotingPowerCap::Uncapped,
+            lock_stake: false,
+        };
 
-    #[test]
-    fn test_has_voted() {}
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+        let init_msg = PollInitMsg {
+            config: with_config(5, 3300, 0),
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env, init_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::DurationOutOfBounds {
+                min: 100,
+                max: 100_000,
+                duration: 5,
+            })
+        );
 
-    #[test]
-    fn test_num_of_voters() {}
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+        let init_msg = PollInitMsg {
+            config: with_config(1000, 0, 0),
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env, init_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::PercentageOutOfBounds {
+                name: "quorum".to_string(),
+                min: 1000,
+                max: 5000,
+                value: 0,
+            })
+        );
 
-    #[test]
-    fn test_query_vote() {}
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+        let init_msg = PollInitMsg {
+            config: PollConfig {
+                veto_threshold: 500,
+                ..with_config(1000, 3300, 0)
+            },
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env, init_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::PercentageOutOfBounds {
+                name: "veto threshold".to_string(),
+                min: 1000,
+                max: 5000,
+                value: 500,
+            })
+        );
 
-    #[test]
-    fn test_update_voting_power() {}
+        // Bounds supplied by the instantiator are validated as well
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+        let mut permissive_bounds = mock_config_bounds();
+        permissive_bounds.max_threshold = 15_000;
+        let init_msg = PollInitMsg {
+            config: with_config(1000, 3300, 12_000),
+            config_bounds: permissive_bounds,
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env, init_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::InvalidPercentageBounds {
+                name: "threshold".to_string(),
+                min: 0,
+                max: 15_000,
+            })
+        );
+    }
 
     #[test]
-    fn test_vote_after_ended() {}
+    fn test_init_invalid_abstain_choice() {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+        let init_msg = PollInitMsg {
+            abstain_choice: Some(2),
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env, init_msg).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::InvalidSpecialChoice {
+                name: "abstain".to_string(),
+                choice: 2,
+            })
+        );
+    }
+
+    #[test]
+    fn test_tally_before_ended() {
+        let mut deps = init_with_total_staked(1000);
+        update_vote(
+            &mut deps,
+            &HumanAddr("user".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 500,
+            },
+        )
+        .unwrap();
+
+        let not_finalized = StdError::from(ContractError::NotFinalized);
+        assert_eq!(query_tally(&deps).unwrap_err(), not_finalized);
+        assert_eq!(query_result(&deps).unwrap_err(), not_finalized);
+        let hash = hex::encode([0u8; 32]);
+        let err = finalize(&mut deps, mock_env("rev1", &[], 1, 999), hash).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::VoteNotEnded));
+
+        finalize_by_committee(&mut deps, 1001);
+        match from_binary(&query_tally(&deps).unwrap()).unwrap() {
+            QueryAnswer::Tally { tally, .. } => assert_eq!(tally, vec![Uint128(500), Uint128(0)]),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    #[test]
+    fn test_update_vote_checked_arithmetic() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        let user = HumanAddr("user".into());
+
+        let err = update_vote(
+            &mut deps,
+            &user,
+            None,
+            Vote {
+                choice: 2,
+                voting_power: 100,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::InvalidChoice { choice: 2 })
+        );
+
+        update_vote(
+            &mut deps,
+            &user,
+            None,
+            Vote {
+                choice: 0,
+                voting_power: u128::MAX,
+            },
+        )
+        .unwrap();
+        let err = update_vote(
+            &mut deps,
+            &HumanAddr("other user".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 1,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::TallyOverflow));
+
+        // A previous ballot larger than its choice's tally means storage is inconsistent
+        let err = update_vote(
+            &mut deps,
+            &user,
+            Some(Vote {
+                choice: 1,
+                voting_power: 100,
+            }),
+            Vote {
+                choice: 0,
+                voting_power: 100,
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::TallyUnderflow));
+    }
+
+    #[test]
+    fn test_tally_below_quorum() {
+        let mut deps = init_with_total_staked(1000);
+        update_vote(
+            &mut deps,
+            &HumanAddr("user".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 329,
+            },
+        )
+        .unwrap();
+
+        let answer = finalize_by_committee(&mut deps, 1001);
+        assert!(answer.finalized);
+        assert_eq!(answer.valid, Some(false));
+        assert_eq!(
+            answer.result,
+            Some(PollResult {
+                outcome: Outcome::QuorumNotMet,
+                winning_choice: None,
+                total_voting_power: Uint128(329),
+                participating_supply: Uint128(1000),
+                turnout: 3290,
+                choice_percentages: None,
+            })
+        );
+
+        let err = query_tally(&deps).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::QuorumNotPassed));
+    }
+
+    #[test]
+    fn test_tally_at_quorum() {
+        let mut deps = init_with_total_staked(1000);
+        update_vote(
+            &mut deps,
+            &HumanAddr("user".into()),
+            None,
+            Vote {
+                choice: 1,
+                voting_power: 330,
+            },
+        )
+        .unwrap();
+
+        // Quorum is inclusive, so exactly 33% is enough
+        let answer = finalize_by_committee(&mut deps, 1001);
+        assert!(answer.finalized);
+        assert_eq!(answer.valid, Some(true));
+        assert_eq!(answer.tally, Some(vec![Uint128(0), Uint128(330)]));
+
+        let expected = PollResult {
+            outcome: Outcome::Passed,
+            winning_choice: Some(1),
+            total_voting_power: Uint128(330),
+            participating_supply: Uint128(1000),
+            turnout: 3300,
+            choice_percentages: Some(vec![0, 10_000]),
+        };
+        assert_eq!(answer.result, Some(expected.clone()));
+
+        let res = query_result(&deps).unwrap();
+        assert_eq!(
+            res,
+            to_binary(&QueryAnswer::Result {
+                choices: vec!["Yes".into(), "No".into()],
+                result: expected,
+            })
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_event_logs() {
+        let (init_result, _) = init_helper();
+        assert_eq!(
+            init_result.unwrap().log,
+            vec![
+                log("action", "init"),
+                log("poll_id", MOCK_CONTRACT_ADDR),
+                log("phase", "active"),
+            ]
+        );
+
+        let mut deps = init_with_total_staked(1000);
+        update_vote(
+            &mut deps,
+            &HumanAddr("voter".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 400,
+            },
+        )
+        .unwrap();
+
+        // Voters and non-voters are indistinguishable
+        let expected = vec![
+            log("action", "update_voting_power"),
+            log("poll_id", MOCK_CONTRACT_ADDR),
+        ];
+        for staker in &["voter", "non-voter"] {
+            let res = update_voting_power(
+                &mut deps,
+                mock_env("factory", &[], 1, 500),
+                HumanAddr(staker.to_string()),
+                500,
+                None,
+            )
+            .unwrap();
+            assert_eq!(res.log, expected);
+        }
+
+        let hash = hex::encode([0u8; 32]);
+        let res = finalize(&mut deps, mock_env("rev1", &[], 1, 1001), hash.clone()).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "finalize"),
+                log("poll_id", MOCK_CONTRACT_ADDR),
+                log("num_revealed", "1"),
+            ]
+        );
+
+        let res = finalize(&mut deps, mock_env("rev2", &[], 1, 1001), hash).unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                log("action", "finalize"),
+                log("poll_id", MOCK_CONTRACT_ADDR),
+                log("num_revealed", "2"),
+                log("phase", "finalized"),
+                log("outcome", "passed"),
+                log("winning_choice", "0"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_finalize_nothing_staked() {
+        let mut deps = init_with_total_staked(0);
+
+        let answer = finalize_by_committee(&mut deps, 1001);
+        assert!(answer.finalized);
+        assert_eq!(answer.result.unwrap().outcome, Outcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn test_quorum_denominator_frozen_at_close() {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: StakingPoolBalancesQuerier {
+                total_locked: 2000,
+                balance: 1000,
+            },
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), mock_init_msg()).unwrap();
+        update_vote(
+            &mut deps,
+            &HumanAddr("user".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 1000,
+            },
+        )
+        .unwrap();
+
+        // Someone who didn't vote stakes more while the poll is active
+        deps.querier.total_locked = 4000;
+        update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 1, 500),
+            HumanAddr("staker".into()),
+            3000,
+            None,
+        )
+        .unwrap();
+
+        // And withdraws after it closed, which would otherwise meet the quorum
+        deps.querier.total_locked = 1000;
+        let result = finalize_by_committee(&mut deps, 1001).result.unwrap();
+        assert_eq!(result.participating_supply, Uint128(4000));
+        assert_eq!(result.turnout, 2500);
+        assert_eq!(result.outcome, Outcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn test_has_voted() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+
+        update_vote(
+            &mut deps,
+            &HumanAddr("user".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 100,
+            },
+        )
+        .unwrap();
+
+        let has_voted =
+            |deps: &Extern<MockStorage, MockApi, MockQuerier>, voter: &str| match from_binary(
+                &query_has_voted(deps, HumanAddr(voter.into()), None).unwrap(),
+            )
+            .unwrap()
+            {
+                QueryAnswer::HasVoted { has_voted } => has_voted,
+                _ => panic!("unexpected answer"),
+            };
+        assert!(has_voted(&deps, "user"));
+        assert!(!has_voted(&deps, "someone else"));
+    }
+
+    #[test]
+    fn test_has_voted_private_participation() {
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = PollInitMsg {
+            private_participation: true,
+            ..mock_init_msg()
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), init_msg).unwrap();
+
+        let err = query_has_voted(&deps, HumanAddr("user".into()), None).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::ViewingKeyRequired));
+
+        // The key is checked against the staking pool, which doesn't exist here
+        assert!(query_has_voted(&deps, HumanAddr("user".into()), Some("key".to_string())).is_err());
+    }
+
+    #[test]
+    fn test_num_of_voters() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+
+        let num_of_voters = |deps: &Extern<MockStorage, MockApi, MockQuerier>| match from_binary(
+            &query_num_of_voters(deps).unwrap(),
+        )
+        .unwrap()
+        {
+            QueryAnswer::NumberOfVoters { count } => count,
+            _ => panic!("unexpected answer"),
+        };
+        assert_eq!(num_of_voters(&deps), 0);
+
+        // Changing a ballot doesn't count as another voter
+        for (voter, choice) in &[("alice", 0), ("bob", 1), ("alice", 1)] {
+            let prev_vote = read_vote(&deps, &HumanAddr(voter.to_string())).ok();
+            update_vote(
+                &mut deps,
+                &HumanAddr(voter.to_string()),
+                prev_vote,
+                Vote {
+                    choice: *choice,
+                    voting_power: 100,
+                },
+            )
+            .unwrap();
+        }
+        assert_eq!(num_of_voters(&deps), 2);
+    }
+
+    fn init_with_balances(
+        total_locked: u128,
+        balance: u128,
+    ) -> Extern<MockStorage, MockApi, StakingPoolBalancesQuerier> {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: StakingPoolBalancesQuerier {
+                total_locked,
+                balance,
+            },
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), mock_init_msg()).unwrap();
+
+        deps
+    }
+
+    #[test]
+    fn test_query_vote() {
+        let mut deps = init_with_balances(2000, 1000);
+        let user = || HumanAddr("user".into());
+        assert!(query_vote(&deps, user(), "key".to_string()).is_err());
+
+        vote(
+            &mut deps,
+            mock_env("user", &[], 1, 100),
+            1,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap();
+        match from_binary(&query_vote(&deps, user(), "key".to_string()).unwrap()).unwrap() {
+            QueryAnswer::Vote {
+                choice,
+                voting_power,
+                uncapped_voting_power,
+            } => {
+                assert_eq!(choice, 1);
+                assert_eq!(voting_power, Uint128(1000));
+                assert_eq!(uncapped_voting_power, Uint128(1000));
+            }
+            _ => panic!("unexpected answer"),
+        }
+        assert!(query_vote(&deps, HumanAddr("someone else".into()), "key".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_update_voting_power() {
+        let mut deps = init_with_balances(2000, 1000);
+        vote(
+            &mut deps,
+            mock_env("user", &[], 1, 100),
+            0,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap();
+        let user = || HumanAddr("user".into());
+        let tally = |deps: &Extern<_, _, _>| -> Vec<u128> {
+            TypedStore::attach(&deps.storage).load(TALLY_KEY).unwrap()
+        };
+
+        // Only the factory forwards updates
+        let err = update_voting_power(&mut deps, mock_env("user", &[], 2, 200), user(), 0, None)
+            .unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        let res = update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 2, 200),
+            user(),
+            300,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            res.log,
+            vec![
+                events::action(events::UPDATE_VOTING_POWER),
+                log(events::POLL_ID, MOCK_CONTRACT_ADDR),
+            ]
+        );
+        assert_eq!(read_vote(&deps, &user()).unwrap().voting_power, 300);
+        assert_eq!(tally(&deps), vec![300, 0]);
+
+        // Stakers who didn't vote are logged the same, but don't get a ballot
+        let res = update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 3, 300),
+            HumanAddr("staker".into()),
+            500,
+            None,
+        )
+        .unwrap();
+        assert_eq!(res.log.len(), 2);
+        assert!(read_vote(&deps, &HumanAddr("staker".into())).is_err());
+        assert_eq!(tally(&deps), vec![300, 0]);
+
+        let err = update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 4, 1101),
+            user(),
+            100,
+            None,
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::VoteEnded));
+        assert_eq!(tally(&deps), vec![300, 0]);
+    }
+
+    #[test]
+    fn test_vote_after_ended() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+
+        let env = mock_env("user", &[], 0, 1001);
+        let err = vote(&mut deps, env, 0, "key".to_string(), "salt".to_string()).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::VoteEnded));
+    }
+
+    #[test]
+    fn test_revote_policy() {
+        let init_with_policy = |revote_policy: RevotePolicy| {
+            let mut deps = Extern {
+                storage: MockStorage::new(),
+                api: MockApi::new(20),
+                querier: StakingPoolBalancesQuerier {
+                    total_locked: 1000,
+                    balance: 100,
+                },
+            };
+            let mut msg = mock_init_msg();
+            msg.config.revote_policy = revote_policy;
+            init(&mut deps, mock_env("factory", &[], 0, 0), msg).unwrap();
+            deps
+        };
+        let vote_at = |deps: &mut Extern<MockStorage, MockApi, StakingPoolBalancesQuerier>,
+                       time: u64,
+                       choice: u8| {
+            vote(
+                deps,
+                mock_env("user", &[], 1, time),
+                choice,
+                "key".to_string(),
+                "salt".to_string(),
+            )
+        };
+
+        let mut deps = init_with_policy(RevotePolicy::Allowed);
+        for choice in &[0, 1, 0, 1] {
+            vote_at(&mut deps, 10, *choice).unwrap();
+        }
+        assert_eq!(
+            read_vote(&deps, &HumanAddr("user".into())).unwrap().choice,
+            1
+        );
+
+        let mut deps = init_with_policy(RevotePolicy::Disallowed);
+        vote_at(&mut deps, 10, 0).unwrap();
+        let err = vote_at(&mut deps, 20, 1).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::RevoteNotAllowed));
+        assert_eq!(
+            read_vote(&deps, &HumanAddr("user".into())).unwrap().choice,
+            0
+        );
+
+        let mut deps = init_with_policy(RevotePolicy::Limited { max_revotes: 2 });
+        vote_at(&mut deps, 10, 0).unwrap();
+        vote_at(&mut deps, 20, 1).unwrap();
+        vote_at(&mut deps, 30, 0).unwrap();
+        let err = vote_at(&mut deps, 40, 1).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::RevoteLimitReached { max_revotes: 2 })
+        );
+        assert_eq!(read_revotes(&deps, &HumanAddr("user".into())).unwrap(), 2);
+
+        // The poll ends at 1000, so ballots lock at 900
+        let mut deps = init_with_policy(RevotePolicy::UntilLock { lock_period: 100 });
+        vote_at(&mut deps, 950, 0).unwrap(); // First ballots are always accepted
+        let mut deps = init_with_policy(RevotePolicy::UntilLock { lock_period: 100 });
+        vote_at(&mut deps, 10, 0).unwrap();
+        vote_at(&mut deps, 899, 1).unwrap();
+        let err = vote_at(&mut deps, 900, 0).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::BallotsLocked));
+        assert_eq!(
+            read_vote(&deps, &HumanAddr("user".into())).unwrap().choice,
+            1
+        );
+
+        let mut msg = mock_init_msg();
+        msg.config.revote_policy = RevotePolicy::UntilLock { lock_period: 1000 };
+        let err = init(
+            &mut mock_dependencies(20, &[]),
+            mock_env("factory", &[], 0, 0),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::LockPeriodTooLong {
+                lock_period: 1000,
+                duration: 1000
+            })
+        );
+    }
+
+    /// Answers the staking pool's `TotalLocked` and `StakeInfo` queries
+    struct StakeInfoQuerier {
+        staked_since: u64,
+    }
+
+    impl Querier for StakeInfoQuerier {
+        fn raw_query(&self, request: &[u8]) -> QuerierResult {
+            let msg = match from_slice(request) {
+                Ok(QueryRequest::<Empty>::Wasm(WasmQuery::Smart { msg, .. })) => msg,
+                _ => panic!("unexpected query"),
+            };
+            Ok(match from_binary(&msg).unwrap() {
+                LPStakingQueryMsg::TotalLocked { .. } => {
+                    to_binary(&LPStakingQueryAnswer::TotalLocked {
+                        amount: Uint128(1000),
+                    })
+                }
+                LPStakingQueryMsg::StakeInfo { .. } => {
+                    to_binary(&LPStakingQueryAnswer::StakeInfo {
+                        amount: Uint128(100),
+                        staked_since: self.staked_since,
+                    })
+                }
+                LPStakingQueryMsg::Balance { .. } => panic!("unexpected query"),
+            })
+        }
+    }
+
+    #[test]
+    fn test_stake_age_voting_power() {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: StakeInfoQuerier { staked_since: 0 },
+        };
+        let mut msg = mock_init_msg();
+        msg.config.voting_power_mode = VotingPowerMode::StakeAge {
+            max_multiplier: 30_000,
+            boost_period: 400,
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), msg).unwrap();
+
+        // Staked for 100 seconds out of 400, so boosted by a quarter of the extra 2x
+        vote(
+            &mut deps,
+            mock_env("user", &[], 1, 100),
+            0,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap();
+        let user = HumanAddr("user".into());
+        assert_eq!(read_vote(&deps, &user).unwrap().voting_power, 150);
+
+        // The pool reports the new balance's age
+        update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 2, 500),
+            user.clone(),
+            200,
+            Some(0),
+        )
+        .unwrap();
+        assert_eq!(read_vote(&deps, &user).unwrap().voting_power, 600); // Capped at 3x
+        let tally: Vec<u128> = TypedStore::attach(&deps.storage).load(TALLY_KEY).unwrap();
+        assert_eq!(tally, vec![600, 0]);
+
+        // Stake of unknown age isn't boosted
+        update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 3, 600),
+            user.clone(),
+            200,
+            None,
+        )
+        .unwrap();
+        assert_eq!(read_vote(&deps, &user).unwrap().voting_power, 200);
+
+        let mut msg = mock_init_msg();
+        msg.config.voting_power_mode = VotingPowerMode::StakeAge {
+            max_multiplier: 9_000,
+            boost_period: 400,
+        };
+        let err = init(
+            &mut mock_dependencies(20, &[]),
+            mock_env("factory", &[], 0, 0),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::InvalidStakeAgeBoost {
+                max_multiplier: 9_000,
+                boost_period: 400
+            })
+        );
+    }
+
+    /// Answers the staking pool's `TotalLocked` and `Balance` queries
+    struct StakingPoolBalancesQuerier {
+        total_locked: u128,
+        balance: u128,
+    }
+
+    impl Querier for StakingPoolBalancesQuerier {
+        fn raw_query(&self, request: &[u8]) -> QuerierResult {
+            let msg = match from_slice(request) {
+                Ok(QueryRequest::<Empty>::Wasm(WasmQuery::Smart { msg, .. })) => msg,
+                _ => panic!("unexpected query"),
+            };
+            Ok(match from_binary(&msg).unwrap() {
+                LPStakingQueryMsg::TotalLocked { .. } => {
+                    to_binary(&LPStakingQueryAnswer::TotalLocked {
+                        amount: Uint128(self.total_locked),
+                    })
+                }
+                LPStakingQueryMsg::Balance { .. } => to_binary(&LPStakingQueryAnswer::Balance {
+                    amount: Uint128(self.balance),
+                }),
+                LPStakingQueryMsg::StakeInfo { .. } => panic!("unexpected query"),
+            })
+        }
+    }
+
+    #[test]
+    fn test_voting_power_cap() {
+        let init_with_bounded_cap = |power_cap: VotingPowerCap, max_power_cap: VotingPowerCap| {
+            let mut deps = Extern {
+                storage: MockStorage::new(),
+                api: MockApi::new(20),
+                querier: StakingPoolBalancesQuerier {
+                    total_locked: 2000,
+                    balance: 1000,
+                },
+            };
+            let mut msg = mock_init_msg();
+            msg.config.power_cap = power_cap;
+            msg.config_bounds.max_power_cap = max_power_cap;
+            init(&mut deps, mock_env("factory", &[], 0, 0), msg).map(|_| deps)
+        };
+        let init_with_cap =
+            |power_cap: VotingPowerCap| init_with_bounded_cap(power_cap, VotingPowerCap::Uncapped);
+        let query_powers = |deps: &Extern<_, _, _>| {
+            let answer = query_vote(deps, HumanAddr("user".into()), "key".to_string()).unwrap();
+            match from_binary(&answer).unwrap() {
+                QueryAnswer::Vote {
+                    voting_power,
+                    uncapped_voting_power,
+                    ..
+                } => (voting_power.u128(), uncapped_voting_power.u128()),
+                _ => panic!("unexpected answer"),
+            }
+        };
+
+        // A quarter of the 2000 staked
+        let mut deps = init_with_cap(VotingPowerCap::ShareOfTotal { share: 2500 }).unwrap();
+        vote(
+            &mut deps,
+            mock_env("user", &[], 1, 100),
+            0,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap();
+        assert_eq!(query_powers(&deps), (500, 1000));
+        let tally: Vec<u128> = TypedStore::attach(&deps.storage).load(TALLY_KEY).unwrap();
+        assert_eq!(tally, vec![500, 0]);
+
+        update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 2, 200),
+            HumanAddr("user".into()),
+            300,
+            None,
+        )
+        .unwrap();
+        assert_eq!(query_powers(&deps), (300, 300));
+
+        let mut deps = init_with_cap(VotingPowerCap::Absolute {
+            amount: Uint128(200),
+        })
+        .unwrap();
+        vote(
+            &mut deps,
+            mock_env("user", &[], 1, 100),
+            1,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap();
+        assert_eq!(query_powers(&deps), (200, 1000));
+
+        for invalid_cap in &[
+            VotingPowerCap::Absolute { amount: Uint128(0) },
+            VotingPowerCap::ShareOfTotal { share: 0 },
+            VotingPowerCap::ShareOfTotal { share: 10_001 },
+        ] {
+            assert_eq!(
+                init_with_cap(*invalid_cap).err(),
+                Some(StdError::from(ContractError::InvalidVotingPowerCap))
+            );
+        }
+
+        // The admin can require a cap
+        let max_share = VotingPowerCap::ShareOfTotal { share: 2500 };
+        init_with_bounded_cap(VotingPowerCap::ShareOfTotal { share: 1000 }, max_share).unwrap();
+        for loose_cap in &[
+            VotingPowerCap::Uncapped,
+            VotingPowerCap::Absolute {
+                amount: Uint128(200),
+            },
+            VotingPowerCap::ShareOfTotal { share: 2501 },
+        ] {
+            assert_eq!(
+                init_with_bounded_cap(*loose_cap, max_share).err(),
+                Some(StdError::from(ContractError::VotingPowerCapTooLoose))
+            );
+        }
+        assert_eq!(
+            init_with_bounded_cap(
+                VotingPowerCap::Uncapped,
+                VotingPowerCap::ShareOfTotal { share: 0 }
+            )
+            .err(),
+            Some(StdError::from(ContractError::InvalidVotingPowerCap))
+        );
+    }
+
+    /// A pool whose total decays by one every second, like the vote escrow's
+    struct DecayingPoolQuerier;
+
+    impl Querier for DecayingPoolQuerier {
+        fn raw_query(&self, request: &[u8]) -> QuerierResult {
+            let msg = match from_slice(request) {
+                Ok(QueryRequest::<Empty>::Wasm(WasmQuery::Smart { msg, .. })) => msg,
+                _ => panic!("unexpected query"),
+            };
+            Ok(match from_binary(&msg).unwrap() {
+                LPStakingQueryMsg::TotalLocked { as_of } => {
+                    to_binary(&LPStakingQueryAnswer::TotalLocked {
+                        amount: Uint128(5000 - as_of.unwrap() as u128),
+                    })
+                }
+                LPStakingQueryMsg::Balance { .. } => to_binary(&LPStakingQueryAnswer::Balance {
+                    amount: Uint128(100),
+                }),
+                LPStakingQueryMsg::StakeInfo { .. } => panic!("unexpected query"),
+            })
+        }
+    }
+
+    #[test]
+    fn test_closing_total_voting_power() {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: DecayingPoolQuerier,
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), mock_init_msg()).unwrap();
+        let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(
+            closing_total_voting_power(&mut deps, &config).unwrap(),
+            4000
+        );
+
+        // Recorded as of the poll's end, not when the ballot was cast
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: DecayingPoolQuerier,
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), mock_init_msg()).unwrap();
+        vote(
+            &mut deps,
+            mock_env("user", &[], 1, 100),
+            0,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap();
+        let recorded: u128 = TypedStore::attach(&deps.storage)
+            .load(CLOSING_TOTAL_KEY)
+            .unwrap();
+        assert_eq!(recorded, 4000);
+    }
+
+    fn member(address: &str, weight: Option<u128>) -> PollMember {
+        PollMember {
+            address: HumanAddr(address.to_string()),
+            weight: weight.map(Uint128),
+        }
+    }
+
+    #[test]
+    fn test_member_poll() {
+        let member_poll_msg = |members: Vec<PollMember>| PollInitMsg {
+            voting_power_source: VotingPowerSource::Whitelist { members },
+            ..mock_init_msg()
+        };
+
+        for members in &[
+            vec![],
+            vec![member("alice", None), member("alice", Some(2))],
+            vec![member("alice", Some(0))],
+        ] {
+            let err = init(
+                &mut mock_dependencies(20, &[]),
+                mock_env("factory", &[], 0, 0),
+                member_poll_msg(members.clone()),
+            )
+            .unwrap_err();
+            assert_eq!(err, StdError::from(ContractError::InvalidMemberList));
+        }
+        let mut msg = member_poll_msg(vec![member("alice", None)]);
+        msg.config.voting_power_mode = VotingPowerMode::StakeAge {
+            max_multiplier: 20_000,
+            boost_period: 100,
+        };
+        let err = init(
+            &mut mock_dependencies(20, &[]),
+            mock_env("factory", &[], 0, 0),
+            msg,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::StakeAgeWithoutStakingPool)
+        );
+
+        // Nothing is queried from a staking pool, so the default querier is enough
+        let mut deps = mock_dependencies(20, &[]);
+        init(
+            &mut deps,
+            mock_env("factory", &[], 0, 0),
+            member_poll_msg(vec![
+                member("alice", None),
+                member("bob", Some(2)),
+                member("carol", None),
+            ]),
+        )
+        .unwrap();
+
+        let cast = |deps: &mut Extern<_, _, _>, voter: &str, choice: u8| {
+            vote(
+                deps,
+                mock_env(voter, &[], 1, 100),
+                choice,
+                format!("{} key", voter),
+                "salt".to_string(),
+            )
+        };
+        let err = cast(&mut deps, "eve", 0).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::NotAMember));
+        cast(&mut deps, "alice", 0).unwrap();
+        cast(&mut deps, "bob", 1).unwrap();
+
+        // The key they voted with authenticates their queries
+        let alice = HumanAddr("alice".into());
+        assert_eq!(
+            query_vote(&deps, alice.clone(), "alice key".to_string()).unwrap(),
+            to_binary(&QueryAnswer::Vote {
+                choice: 0,
+                voting_power: Uint128(1),
+                uncapped_voting_power: Uint128(1),
+            })
+            .unwrap()
+        );
+        assert_eq!(
+            query_vote(&deps, alice, "bob key".to_string()).unwrap_err(),
+            StdError::unauthorized()
+        );
+        assert_eq!(
+            query_vote(&deps, HumanAddr("carol".into()), "carol key".to_string()).unwrap_err(),
+            StdError::unauthorized()
+        );
+
+        // Staking doesn't change a member's weight
+        update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 2, 200),
+            HumanAddr("bob".into()),
+            1_000_000,
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            read_vote(&deps, &HumanAddr("bob".into()))
+                .unwrap()
+                .voting_power,
+            2
+        );
+
+        // 3 of the members' total weight of 4 voted
+        let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        let hash = hex::encode(config.rolling_hash);
+        finalize(&mut deps, mock_env("rev1", &[], 3, 1000), hash.clone()).unwrap();
+        finalize(&mut deps, mock_env("rev2", &[], 3, 1000), hash).unwrap();
+        let result: PollResult = TypedStore::attach(&deps.storage).load(RESULT_KEY).unwrap();
+        assert_eq!(result.outcome, Outcome::Passed);
+        assert_eq!(result.winning_choice, Some(1));
+        assert_eq!(result.participating_supply, Uint128(4));
+        assert_eq!(result.turnout, 7500);
+    }
 
     #[test]
-    fn test_finalize_before_ended() {}
+    fn test_scheduled_start() {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 100);
+        let init_msg = |start_time: Option<u64>| PollInitMsg {
+            start_time,
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env.clone(), init_msg(Some(50))).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::StartTimeInPast));
+        let err = init(&mut deps, env.clone(), init_msg(Some(604_901))).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::StartTimeTooFar {
+                max_delay: 604_800,
+                delay: 604_801,
+            })
+        );
+
+        init(&mut deps, env, init_msg(Some(500))).unwrap();
+
+        let config: StoredPollConfig = TypedStore::attach(&deps.storage).load(CONFIG_KEY).unwrap();
+        assert_eq!(config.start_timestamp, 500);
+        assert_eq!(config.end_timestamp, 1500);
+
+        // Discussion period
+        assert_eq!(poll_phase(&config, 499), PollPhase::Pending);
+        let env = mock_env("user", &[], 1, 499);
+        let err = vote(&mut deps, env, 0, "key".to_string(), "salt".to_string()).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::VoteNotStarted));
+
+        assert_eq!(poll_phase(&config, 500), PollPhase::Active);
+        assert_eq!(poll_phase(&config, 1500), PollPhase::Active);
+        assert_eq!(poll_phase(&config, 1501), PollPhase::Ended);
+    }
+
+    #[test]
+    fn test_voter_index() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+
+        for (voter, choice) in &[("alice", 0), ("bob", 1), ("alice", 1)] {
+            let prev_vote = read_vote(&deps, &HumanAddr(voter.to_string())).ok();
+            update_vote(
+                &mut deps,
+                &HumanAddr(voter.to_string()),
+                prev_vote,
+                Vote {
+                    choice: *choice,
+                    voting_power: 100,
+                },
+            )
+            .unwrap();
+        }
+
+        assert_eq!(read_voter(&deps, 0).unwrap(), HumanAddr("alice".into()));
+        assert_eq!(read_voter(&deps, 1).unwrap(), HumanAddr("bob".into()));
+        assert!(read_voter(&deps, 2).is_err());
+        // Ballots don't live in the root namespace
+        assert!(deps.storage.get(b"alice").is_none());
+    }
+
+    #[test]
+    fn test_storage_version() {
+        let (init_result, mut deps) = init_helper();
+        assert!(init_result.is_ok());
+        let version: u32 = TypedStore::attach(&deps.storage)
+            .load(STORAGE_VERSION_KEY)
+            .unwrap();
+        assert_eq!(version, STORAGE_VERSION);
+
+        // Version 0 polls don't have the key
+        deps.storage.remove(STORAGE_VERSION_KEY);
+        let err = query(&deps, QueryMsg::NumberOfVoters {}).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::StorageVersionMismatch {
+                found: 0,
+                expected: STORAGE_VERSION,
+            })
+        );
+    }
+
+    #[test]
+    fn test_response_padding() {
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = PollInitMsg {
+            response_block_size: 64,
+            ..mock_init_msg()
+        };
+        init(&mut deps, mock_env("factory", &[], 0, 0), init_msg).unwrap();
+
+        let res = query(&deps, QueryMsg::Choices {}).unwrap();
+        assert_eq!(res.0.len(), 64);
+        let res = query(&deps, QueryMsg::RollingHash {}).unwrap();
+        assert_eq!(res.0.len(), 128);
+
+        let res = handle(
+            &mut deps,
+            mock_env("proposer", &[], 1, 500),
+            PollHandleMsg::Cancel { padding: None },
+        )
+        .unwrap();
+        assert_eq!(res.data.unwrap().0.len(), 64);
+
+        let mut deps = mock_dependencies(20, &[]);
+        let init_msg = PollInitMsg {
+            response_block_size: 0,
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, mock_env("factory", &[], 0, 0), init_msg).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::InvalidResponseBlockSize));
+    }
+
+    #[test]
+    fn test_cancel() {
+        let mut deps = init_with_total_staked(1000);
+
+        let err = query_result(&deps).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::NotFinalized));
+
+        let err = cancel(&mut deps, mock_env("rev1", &[], 1, 500)).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+
+        let err = cancel(&mut deps, mock_env("proposer", &[], 1, 1001)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::CancelAfterEnd));
+
+        cancel(&mut deps, mock_env("proposer", &[], 1, 500)).unwrap();
+
+        let res: QueryAnswer = from_binary(&query_result(&deps).unwrap()).unwrap();
+        match res {
+            QueryAnswer::Result { result, .. } => {
+                assert_eq!(result.outcome, Outcome::Cancelled);
+                assert_eq!(result.choice_percentages, None);
+            }
+            _ => panic!("unexpected query answer"),
+        }
+
+        let err = vote(
+            &mut deps,
+            mock_env("user", &[], 1, 600),
+            0,
+            "key".to_string(),
+            "salt".to_string(),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::VoteEnded));
+
+        // Stake updates are still accepted, so they don't fail the staker's transaction
+        update_voting_power(
+            &mut deps,
+            mock_env("factory", &[], 1, 600),
+            HumanAddr("user".into()),
+            100,
+            None,
+        )
+        .unwrap();
+
+        let err = finalize(
+            &mut deps,
+            mock_env("rev1", &[], 1, 1001),
+            hex::encode([0u8; 32]),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::PollCancelled));
+    }
+
+    #[test]
+    fn test_report_result_to_factory() {
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: StakingPoolQuerier { total_locked: 1000 },
+        };
+        let init_msg = PollInitMsg {
+            init_hook: Some(InitHook {
+                contract_addr: HumanAddr("factory".into()),
+                code_hash: "factory hash".to_string(),
+                msg: Binary::from(b"register".to_vec()),
+            }),
+            ..mock_init_msg()
+        };
+        let res = init(&mut deps, mock_env("factory", &[], 0, 0), init_msg).unwrap();
+        assert_eq!(res.messages.len(), 1);
+
+        update_vote(
+            &mut deps,
+            &HumanAddr("user".into()),
+            None,
+            Vote {
+                choice: 0,
+                voting_power: 500,
+            },
+        )
+        .unwrap();
+
+        let hash = hex::encode([0u8; 32]);
+        let res = finalize(&mut deps, mock_env("rev1", &[], 1, 1001), hash.clone()).unwrap();
+        assert!(res.messages.is_empty());
+
+        let res = finalize(&mut deps, mock_env("rev2", &[], 1, 1001), hash).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr("factory".into()),
+                callback_code_hash: "factory hash".to_string(),
+                msg: to_binary(&PollFactoryHandleMsg::ReportResult {
+                    outcome: Outcome::Passed,
+                    winning_choice: Some(0),
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+    }
+
+    #[test]
+    fn test_lock_stake() {
+        let locking_msg = |init_hook: Option<InitHook>| {
+            let mut msg = PollInitMsg {
+                init_hook,
+                ..mock_init_msg()
+            };
+            msg.config.lock_stake = true;
+            msg
+        };
+        let hook = || {
+            Some(InitHook {
+                contract_addr: HumanAddr("factory".into()),
+                code_hash: "factory hash".to_string(),
+                msg: Binary::from(b"register".to_vec()),
+            })
+        };
+
+        // The lock goes through the factory, to the staking pool
+        let err = init(
+            &mut mock_dependencies(20, &[]),
+            mock_env("factory", &[], 0, 0),
+            locking_msg(None),
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::LockStakeUnsupported));
+        let err = init(
+            &mut mock_dependencies(20, &[]),
+            mock_env("factory", &[], 0, 0),
+            PollInitMsg {
+                voting_power_source: VotingPowerSource::Whitelist {
+                    members: vec![member("user", None)],
+                },
+                ..locking_msg(hook())
+            },
+        )
+        .unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::LockStakeUnsupported));
+
+        let mut deps = Extern {
+            storage: MockStorage::new(),
+            api: MockApi::new(20),
+            querier: StakingPoolBalancesQuerier {
+                total_locked: 2000,
+                balance: 1000,
+            },
+        };
+        init(
+            &mut deps,
+            mock_env("factory", &[], 0, 0),
+            locking_msg(hook()),
+        )
+        .unwrap();
+
+        let cast = |deps: &mut Extern<_, _, _>| {
+            vote(
+                deps,
+                mock_env("user", &[], 1, 100),
+                0,
+                "key".to_string(),
+                "salt".to_string(),
+            )
+        };
+        let res = cast(&mut deps).unwrap();
+        assert_eq!(
+            res.messages,
+            vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr("factory".into()),
+                callback_code_hash: "factory hash".to_string(),
+                msg: to_binary(&PollFactoryHandleMsg::LockStake {
+                    voter: HumanAddr("user".into()),
+                })
+                .unwrap(),
+                send: vec![],
+            })]
+        );
+
+        // Already locked
+        let res = cast(&mut deps).unwrap();
+        assert!(res.messages.is_empty());
+    }
+
+    #[test]
+    fn test_init_veto_choice() {
+        let mut deps = mock_dependencies(20, &[]);
+        let env = mock_env("factory", &[], 0, 0);
+        let init_msg = PollInitMsg {
+            abstain_choice: Some(1),
+            veto_choice: Some(1),
+            ..mock_init_msg()
+        };
+        let err = init(&mut deps, env, init_msg).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::AbstainIsVeto));
+    }
+
+    #[test]
+    fn test_finalize_before_ended() {
+        let mut deps = init_with_total_staked(1000);
+
+        let env = mock_env("rev1", &[], 1, 999);
+        let err = finalize(&mut deps, env, hex::encode([0u8; 32])).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::VoteNotEnded));
+    }
 }
@@ -2,6 +2,7 @@ pub mod contract;
 pub mod msg;
 pub mod querier;
 pub mod state;
+pub mod tally;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
@@ -1,4 +1,4 @@
-use crate::state::StoredPollConfig;
+use crate::state::{PollResult, StoredPollConfig};
 use cosmwasm_std::{HumanAddr, Uint128};
 use schemars::JsonSchema;
 use scrt_finance::secret_vote_types::{PollMetadata, RevealCommittee};
@@ -9,6 +9,7 @@ use serde::{Deserialize, Serialize};
 pub struct FinalizeAnswer {
     pub finalized: bool,
     pub valid: Option<bool>,
+    pub result: Option<PollResult>,
     pub choices: Option<Vec<String>>,
     pub tally: Option<Vec<Uint128>>,
 }
@@ -18,18 +19,30 @@ pub struct FinalizeAnswer {
 pub enum QueryMsg {
     // Public
     Choices {},
-    VoteInfo {},
-    HasVoted { voter: HumanAddr },
+    // `current_time` is a Linux epoch. Without it, the phase is only known once the poll is final
+    VoteInfo {
+        current_time: Option<u64>,
+    },
+    HasVoted {
+        voter: HumanAddr,
+        key: Option<String>, // Required if the poll's participation is private
+    },
     Tally {},
+    Result {},
     NumberOfVoters {},
     RevealCommittee {},
     Revealed {},
     RollingHash {},
 
     // Authenticated
-    Vote { voter: HumanAddr, key: String },
+    Vote {
+        voter: HumanAddr,
+        key: String,
+    },
 }
 
+// Answers are serialized right away, so VoteInfo's full config isn't worth boxing
+#[allow(clippy::large_enum_variant)]
 #[derive(Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryAnswer {
@@ -40,6 +53,7 @@ pub enum QueryAnswer {
         metadata: PollMetadata,
         config: StoredPollConfig,
         reveal_com: RevealCommittee,
+        phase: Option<PollPhase>, // Unknown if the query has no `current_time` and it's still open
     },
     HasVoted {
         has_voted: bool,
@@ -48,9 +62,14 @@ pub enum QueryAnswer {
         choices: Vec<String>,
         tally: Vec<Uint128>,
     },
+    Result {
+        choices: Vec<String>,
+        result: PollResult,
+    },
     Vote {
         choice: u8,
-        voting_power: Uint128,
+        voting_power: Uint128,          // What the ballot counts for
+        uncapped_voting_power: Uint128, // Before the poll's voting power cap
     },
     NumberOfVoters {
         count: u64,
@@ -68,6 +87,15 @@ pub enum QueryAnswer {
     },
 }
 
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum PollPhase {
+    Pending,   // Announced, voting hasn't started yet
+    Active,    // Voting is open
+    Ended,     // Voting is closed, waiting for the reveal committee
+    Finalized, // Results were revealed
+}
+
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum ResponseStatus {
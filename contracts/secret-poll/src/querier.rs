@@ -1,26 +1,30 @@
-use crate::state::STAKING_POOL_KEY;
 use cosmwasm_std::{
-    to_binary, Api, Extern, Querier, QueryRequest, StdError, StdResult, Storage, WasmQuery,
+    to_binary, Api, Extern, HumanAddr, Querier, QueryRequest, StdError, StdResult, Storage,
+    WasmQuery,
 };
+use scrt_finance::errors::ContractError;
 use scrt_finance::lp_staking_msg::{LPStakingQueryAnswer, LPStakingQueryMsg};
 use scrt_finance::types::SecretContract;
-use secret_toolkit::storage::TypedStore;
 
-pub fn query_staking_balance<S: Storage, A: Api, Q: Querier>(
+/// Returns the staker's balance and its stake age, as the balance-weighted average deposit time
+pub fn query_stake_info<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
-) -> StdResult<u128> {
-    let staking_pool: SecretContract = TypedStore::attach(&deps.storage).load(STAKING_POOL_KEY)?;
-
+    staking_pool: &SecretContract,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<(u128, u64)> {
     let response = deps.querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
-        callback_code_hash: staking_pool.contract_hash,
-        contract_addr: staking_pool.address,
-        msg: to_binary(&LPStakingQueryMsg::TotalLocked {})?,
+        callback_code_hash: staking_pool.contract_hash.clone(),
+        contract_addr: staking_pool.address.clone(),
+        msg: to_binary(&LPStakingQueryMsg::StakeInfo { address, key })?,
     }))?;
 
     match response {
-        LPStakingQueryAnswer::TotalLocked { amount } => Ok(amount.u128()),
-        _ => Err(StdError::generic_err(
-            "something is wrong with the lp staking contract..",
-        )),
+        LPStakingQueryAnswer::StakeInfo {
+            amount,
+            staked_since,
+        } => Ok((amount.u128(), staked_since)),
+        LPStakingQueryAnswer::ViewingKeyError { .. } => Err(StdError::unauthorized()),
+        _ => Err(ContractError::UnexpectedStakingPoolAnswer.into()),
     }
 }
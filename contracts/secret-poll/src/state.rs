@@ -1,16 +1,40 @@
-use cosmwasm_std::{Api, Extern, HumanAddr, Querier, StdResult, Storage};
+use cosmwasm_std::{Api, Extern, HumanAddr, Querier, StdResult, Storage, Uint128};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
 use schemars::JsonSchema;
-use scrt_finance::secret_vote_types::RevealCommittee;
+use scrt_finance::errors::ContractError;
+use scrt_finance::secret_vote_types::{
+    Comparison, Outcome, RevealCommittee, RevotePolicy, VotingPowerCap, VotingPowerMode,
+};
+use scrt_finance::voting_power::VotingPowerSource;
 use secret_toolkit::storage::{TypedStore, TypedStoreMut};
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
 
 pub const OWNER_KEY: &[u8] = b"owner";
 pub const TALLY_KEY: &[u8] = b"tally";
 pub const METADATA_KEY: &[u8] = b"metadata";
 pub const CONFIG_KEY: &[u8] = b"config";
 pub const STAKING_POOL_KEY: &[u8] = b"stakingpool";
+pub const VOTING_POWER_SOURCE_KEY: &[u8] = b"votingpowersource";
+pub const CLOSING_TOTAL_KEY: &[u8] = b"closingtotal";
 pub const NUM_OF_VOTERS_KEY: &[u8] = b"numofvoters";
 pub const REVEAL_CONFIG: &[u8] = b"revealconfig";
+pub const RESULT_KEY: &[u8] = b"result";
+pub const FACTORY_KEY: &[u8] = b"factory";
+pub const RESPONSE_BLOCK_SIZE_KEY: &[u8] = b"responseblocksize";
+pub const STORAGE_VERSION_KEY: &[u8] = b"storageversion";
+pub const PREFIX_BALLOTS: &[u8] = b"ballots";
+pub const PREFIX_VOTERS: &[u8] = b"voters";
+pub const PREFIX_REVOTES: &[u8] = b"revotes";
+pub const PREFIX_UNCAPPED_POWER: &[u8] = b"uncappedpower";
+pub const PREFIX_MEMBER_KEYS: &[u8] = b"memberkeys";
+
+// Version 0 kept ballots at the voter's address in the root namespace, where they could collide
+// with the keys above. Version 1 moved them under `PREFIX_BALLOTS` and added the voter index.
+// Contracts on this chain can't be migrated, so version 0 polls stay on their layout, running the
+// code they were created with
+pub const STORAGE_VERSION: u32 = 1;
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct Vote {
@@ -20,15 +44,36 @@ pub struct Vote {
 
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct StoredPollConfig {
-    pub end_timestamp: u64, // In seconds
-    pub quorum: u8,         // X/100% (percentage)
-    pub min_threshold: u8,  // X/100% (percentage)
+    pub start_timestamp: u64, // In seconds
+    pub end_timestamp: u64,   // In seconds
+    pub quorum: u16,          // X/10000 (basis points)
+    pub min_threshold: u16,   // X/10000 (basis points)
+    pub quorum_comparison: Comparison,
+    pub threshold_comparison: Comparison,
     pub choices: Vec<String>,
+    pub abstain_choice: Option<u8>,
+    pub veto_choice: Option<u8>,
+    pub veto_threshold: u16, // X/10000 (basis points)
+    pub revote_policy: RevotePolicy,
+    pub voting_power_mode: VotingPowerMode,
+    pub power_cap: VotingPowerCap,
+    pub lock_stake: bool,
+    pub private_participation: bool,
     pub finalized: bool,
-    pub valid: bool,
+    pub valid: bool, // Quorum was met, so the tally can be revealed
     pub rolling_hash: [u8; 32],
 }
 
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct PollResult {
+    pub outcome: Outcome,
+    pub winning_choice: Option<u8>,
+    pub total_voting_power: Uint128,          // Sum of all ballots
+    pub participating_supply: Uint128,        // Total voting power when the poll closed
+    pub turnout: u16,                         // X/10000 (basis points), rounded down
+    pub choice_percentages: Option<Vec<u16>>, // X/10000 (basis points). Hidden if quorum wasn't met
+}
+
 #[derive(Serialize, Deserialize, JsonSchema)]
 pub struct StoredRevealConfig {
     pub committee: RevealCommittee,
@@ -42,7 +87,8 @@ pub fn store_vote<S: Storage, A: Api, Q: Querier>(
     choice: u8,
     voting_power: u128,
 ) -> StdResult<()> {
-    TypedStoreMut::attach(&mut deps.storage).store(
+    let mut ballots = PrefixedStorage::new(PREFIX_BALLOTS, &mut deps.storage);
+    TypedStoreMut::attach(&mut ballots).store(
         voter.0.as_bytes(),
         &Vote {
             choice,
@@ -57,5 +103,132 @@ pub fn read_vote<S: Storage, A: Api, Q: Querier>(
     deps: &Extern<S, A, Q>,
     voter: &HumanAddr,
 ) -> StdResult<Vote> {
-    Ok(TypedStore::attach(&deps.storage).load(voter.0.as_bytes())?)
+    let ballots = ReadonlyPrefixedStorage::new(PREFIX_BALLOTS, &deps.storage);
+    TypedStore::attach(&ballots).load(voter.0.as_bytes())
+}
+
+/// Appends a first-time voter to the voter index and bumps the number of voters
+pub fn add_voter<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    voter: &HumanAddr,
+) -> StdResult<()> {
+    let num_of_voters: u64 = TypedStore::attach(&deps.storage).load(NUM_OF_VOTERS_KEY)?;
+    let mut voters = PrefixedStorage::new(PREFIX_VOTERS, &mut deps.storage);
+    TypedStoreMut::attach(&mut voters).store(&num_of_voters.to_be_bytes(), voter)?;
+    TypedStoreMut::attach(&mut deps.storage).store(NUM_OF_VOTERS_KEY, &(num_of_voters + 1))
+}
+
+/// The number of times a voter changed their ballot
+pub fn read_revotes<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    voter: &HumanAddr,
+) -> StdResult<u32> {
+    let revotes = ReadonlyPrefixedStorage::new(PREFIX_REVOTES, &deps.storage);
+    Ok(TypedStore::attach(&revotes)
+        .may_load(voter.0.as_bytes())?
+        .unwrap_or_default())
+}
+
+pub fn store_revotes<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    voter: &HumanAddr,
+    count: u32,
+) -> StdResult<()> {
+    let mut revotes = PrefixedStorage::new(PREFIX_REVOTES, &mut deps.storage);
+    TypedStoreMut::attach(&mut revotes).store(voter.0.as_bytes(), &count)
+}
+
+/// A voter's power before the poll's cap. Ballots only hold the capped power, which is tallied
+pub fn read_uncapped_power<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    voter: &HumanAddr,
+) -> StdResult<Option<u128>> {
+    let powers = ReadonlyPrefixedStorage::new(PREFIX_UNCAPPED_POWER, &deps.storage);
+    TypedStore::attach(&powers).may_load(voter.0.as_bytes())
+}
+
+pub fn store_uncapped_power<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    voter: &HumanAddr,
+    voting_power: u128,
+) -> StdResult<()> {
+    let mut powers = PrefixedStorage::new(PREFIX_UNCAPPED_POWER, &mut deps.storage);
+    TypedStoreMut::attach(&mut powers).store(voter.0.as_bytes(), &voting_power)
+}
+
+/// Polls created before voting power sources were pluggable only stored their staking pool
+pub fn read_voting_power_source<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<VotingPowerSource> {
+    let source: Option<VotingPowerSource> =
+        TypedStore::attach(&deps.storage).may_load(VOTING_POWER_SOURCE_KEY)?;
+    match source {
+        Some(source) => Ok(source),
+        None => Ok(VotingPowerSource::StakingPool {
+            contract: TypedStore::attach(&deps.storage).load(STAKING_POOL_KEY)?,
+        }),
+    }
+}
+
+/// Whitelisted members have no viewing key elsewhere, so the key they vote with authenticates them
+pub fn set_member_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    member: &HumanAddr,
+    key: &str,
+) -> StdResult<()> {
+    let mut keys = PrefixedStorage::new(PREFIX_MEMBER_KEYS, &mut deps.storage);
+    TypedStoreMut::attach(&mut keys).store(member.0.as_bytes(), &hash_key(key))
+}
+
+pub fn is_member_key_valid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    member: &HumanAddr,
+    key: &str,
+) -> StdResult<bool> {
+    let keys = ReadonlyPrefixedStorage::new(PREFIX_MEMBER_KEYS, &deps.storage);
+    let expected: Option<[u8; 32]> = TypedStore::attach(&keys).may_load(member.0.as_bytes())?;
+
+    // Hash anyway, so a missing key takes as long to check as a wrong one
+    let given = hash_key(key);
+    Ok(match expected {
+        Some(expected) => bool::from(given[..].ct_eq(&expected[..])),
+        None => false,
+    })
+}
+
+fn hash_key(key: &str) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(Sha256::digest(key.as_bytes()).as_slice());
+    hash
+}
+
+/// Voters are indexed by the order they first voted in, from 0 to the number of voters
+pub fn read_voter<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    index: u64,
+) -> StdResult<HumanAddr> {
+    let voters = ReadonlyPrefixedStorage::new(PREFIX_VOTERS, &deps.storage);
+    TypedStore::attach(&voters).load(&index.to_be_bytes())
+}
+
+/// Polls that predate `STORAGE_VERSION_KEY` are at version 0
+pub fn storage_version<S: Storage, A: Api, Q: Querier>(deps: &Extern<S, A, Q>) -> StdResult<u32> {
+    Ok(TypedStore::attach(&deps.storage)
+        .may_load(STORAGE_VERSION_KEY)?
+        .unwrap_or(0))
+}
+
+pub fn require_current_storage_version<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<()> {
+    let version = storage_version(deps)?;
+    if version != STORAGE_VERSION {
+        return Err(ContractError::StorageVersionMismatch {
+            found: version,
+            expected: STORAGE_VERSION,
+        }
+        .into());
+    }
+
+    Ok(())
 }
@@ -0,0 +1,469 @@
+use cosmwasm_std::{StdError, StdResult};
+use scrt_finance::errors::ContractError;
+use scrt_finance::secret_vote_types::{Comparison, Outcome, MAX_BASIS_POINTS};
+
+/// The parameters a tally is judged by
+#[derive(Clone, Debug, PartialEq)]
+pub struct TallyRules {
+    pub quorum: u16,        // X/10000 (basis points) of the total staked
+    pub min_threshold: u16, // X/10000 (basis points) of the non-abstain votes
+    pub quorum_comparison: Comparison,
+    pub threshold_comparison: Comparison,
+    pub abstain_choice: Option<u8>,
+    pub veto_choice: Option<u8>,
+    pub veto_threshold: u16, // X/10000 (basis points) of all votes
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TallyResult {
+    pub outcome: Outcome,
+    pub winning_choice: Option<u8>, // Only set when the outcome is `Passed`
+    pub total_votes: u128,
+    pub total_staked: u128,
+    pub participation: u16,    // X/10000 (basis points), rounded down
+    pub percentages: Vec<u16>, // X/10000 (basis points) of all votes per choice, rounded down
+}
+
+impl TallyResult {
+    pub fn quorum_met(&self) -> bool {
+        self.outcome != Outcome::QuorumNotMet
+    }
+}
+
+/// Computes the result of a poll.
+///
+/// Quorum is measured against `total_staked`, while the threshold is measured as the winning
+/// choice's share of all votes that aren't for the abstain choice. The veto choice can't win,
+/// but vetoes the poll once its share of all votes exceeds the veto threshold. All comparisons
+/// are done by cross-multiplication, so no precision is lost to rounding.
+pub fn compute_result(
+    tally: &[u128],
+    total_staked: u128,
+    rules: &TallyRules,
+) -> StdResult<TallyResult> {
+    if tally.is_empty() {
+        return Err(ContractError::StorageCorrupted.into());
+    }
+
+    let total_votes = checked_sum(tally.iter())?;
+    let participation = basis_points(total_votes, total_staked)?;
+    let percentages = tally
+        .iter()
+        .map(|votes| basis_points(*votes, total_votes))
+        .collect::<StdResult<Vec<u16>>>()?;
+    let result = |outcome: Outcome, winning_choice: Option<u8>| TallyResult {
+        outcome,
+        winning_choice,
+        total_votes,
+        total_staked,
+        participation,
+        percentages: percentages.clone(),
+    };
+
+    // Nothing to measure participation against, so even a zero inclusive quorum isn't met
+    if total_staked == 0
+        || !meets(
+            total_votes,
+            total_staked,
+            rules.quorum,
+            rules.quorum_comparison,
+        )?
+    {
+        return Ok(result(Outcome::QuorumNotMet, None));
+    }
+
+    if let Some(veto) = rules.veto_choice {
+        let veto_votes = tally.get(veto as usize).copied().unwrap_or_default();
+        if veto_votes > 0
+            && meets(
+                veto_votes,
+                total_votes,
+                rules.veto_threshold,
+                Comparison::Exclusive,
+            )?
+        {
+            return Ok(result(Outcome::Vetoed, None));
+        }
+    }
+
+    let decisive = tally
+        .iter()
+        .enumerate()
+        .filter(|(choice, _)| Some(*choice as u8) != rules.abstain_choice);
+    let decisive_votes = checked_sum(decisive.clone().map(|(_, votes)| votes))?;
+
+    let candidates = decisive.filter(|(choice, _)| Some(*choice as u8) != rules.veto_choice);
+
+    let mut leader: Option<(usize, u128)> = None;
+    let mut tie = false;
+    for (choice, votes) in candidates {
+        match leader {
+            Some((_, leading_votes)) if *votes < leading_votes => {}
+            Some((_, leading_votes)) if *votes == leading_votes => tie = true,
+            _ => {
+                leader = Some((choice, *votes));
+                tie = false;
+            }
+        }
+    }
+
+    let (outcome, winning_choice) = match leader {
+        // Nobody voted for anything other than abstain
+        _ if decisive_votes == 0 => (Outcome::Rejected, None),
+        Some(_) if tie => (Outcome::Tie, None),
+        Some((choice, votes)) => {
+            if meets(
+                votes,
+                decisive_votes,
+                rules.min_threshold,
+                rules.threshold_comparison,
+            )? {
+                (Outcome::Passed, Some(choice as u8))
+            } else {
+                (Outcome::Rejected, None)
+            }
+        }
+        None => (Outcome::Rejected, None),
+    };
+
+    Ok(result(outcome, winning_choice))
+}
+
+/// Checks whether `part / whole` reaches `required` basis points
+fn meets(part: u128, whole: u128, required: u16, comparison: Comparison) -> StdResult<bool> {
+    let lhs = part
+        .checked_mul(MAX_BASIS_POINTS as u128)
+        .ok_or_else(|| StdError::from(ContractError::TallyOverflow))?;
+    let rhs = whole
+        .checked_mul(required as u128)
+        .ok_or_else(|| StdError::from(ContractError::TallyOverflow))?;
+
+    Ok(match comparison {
+        Comparison::Inclusive => lhs >= rhs,
+        Comparison::Exclusive => lhs > rhs,
+    })
+}
+
+/// `part / whole` in basis points, rounded down. Returns 0 when `whole` is 0
+pub fn basis_points(part: u128, whole: u128) -> StdResult<u16> {
+    if whole == 0 {
+        return Ok(0);
+    }
+
+    let bps = part
+        .checked_mul(MAX_BASIS_POINTS as u128)
+        .ok_or_else(|| StdError::from(ContractError::TallyOverflow))?
+        / whole;
+
+    // Votes can exceed the current total staked if stakers withdrew after voting
+    Ok(bps.min(MAX_BASIS_POINTS as u128) as u16)
+}
+
+fn checked_sum<'a, I: Iterator<Item = &'a u128>>(mut iter: I) -> StdResult<u128> {
+    iter.try_fold(0u128, |acc, votes| {
+        acc.checked_add(*votes)
+            .ok_or_else(|| StdError::from(ContractError::TallyOverflow))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(quorum: u16, min_threshold: u16) -> TallyRules {
+        TallyRules {
+            quorum,
+            min_threshold,
+            quorum_comparison: Comparison::Inclusive,
+            threshold_comparison: Comparison::Exclusive,
+            abstain_choice: None,
+            veto_choice: None,
+            veto_threshold: 0,
+        }
+    }
+
+    #[test]
+    fn test_quorum_boundary() {
+        // Exactly 33% participation
+        let tally = [20, 13];
+
+        let inclusive = rules(3300, 0);
+        let res = compute_result(&tally, 100, &inclusive).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.participation, 3300);
+        assert_eq!(res.total_votes, 33);
+
+        let exclusive = TallyRules {
+            quorum_comparison: Comparison::Exclusive,
+            ..inclusive
+        };
+        let res = compute_result(&tally, 100, &exclusive).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+        assert_eq!(res.winning_choice, None);
+        assert!(!res.quorum_met());
+    }
+
+    #[test]
+    fn test_quorum_precision() {
+        // 33.33% participation doesn't meet a 33.34% quorum, even though both round to 33%
+        let res = compute_result(&[3333, 0], 10_000, &rules(3334, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+
+        // 3333.5 basis points is rounded down for reporting, but still beats a 3333 quorum
+        let exclusive = TallyRules {
+            quorum_comparison: Comparison::Exclusive,
+            ..rules(3333, 0)
+        };
+        let res = compute_result(&[6667, 0], 20_000, &exclusive).unwrap();
+        assert_eq!(res.participation, 3333);
+        assert_eq!(res.outcome, Outcome::Passed);
+    }
+
+    #[test]
+    fn test_threshold_boundary() {
+        // Winner has exactly 60% of the votes
+        let tally = [60, 40];
+
+        let exclusive = rules(0, 6000);
+        let res = compute_result(&tally, 100, &exclusive).unwrap();
+        assert_eq!(res.outcome, Outcome::Rejected);
+        assert_eq!(res.winning_choice, None);
+        assert!(res.quorum_met());
+
+        let inclusive = TallyRules {
+            threshold_comparison: Comparison::Inclusive,
+            ..exclusive
+        };
+        let res = compute_result(&tally, 100, &inclusive).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+    }
+
+    #[test]
+    fn test_threshold_is_share_of_votes() {
+        // The winner holds 55% of the votes but only 11% of the total staked
+        let res = compute_result(&[11, 9], 100, &rules(2000, 5000)).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+    }
+
+    #[test]
+    fn test_abstain_counts_towards_quorum_only() {
+        let with_abstain = TallyRules {
+            abstain_choice: Some(2),
+            ..rules(5000, 5000)
+        };
+
+        // Without abstain votes, turnout is 30% and quorum isn't met
+        let res = compute_result(&[20, 10, 0], 100, &with_abstain).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+
+        // Abstain votes push turnout to 70%, and the winner has 2/3 of the decisive votes
+        let res = compute_result(&[20, 10, 40], 100, &with_abstain).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+        assert_eq!(res.participation, 7000);
+
+        // Abstain having the most votes doesn't make it the winner
+        let res = compute_result(&[0, 10, 40], 100, &with_abstain).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(1));
+    }
+
+    #[test]
+    fn test_only_abstain_votes() {
+        let with_abstain = TallyRules {
+            abstain_choice: Some(0),
+            ..rules(1000, 0)
+        };
+        let res = compute_result(&[50, 0, 0], 100, &with_abstain).unwrap();
+        assert_eq!(res.outcome, Outcome::Rejected);
+        assert_eq!(res.winning_choice, None);
+    }
+
+    #[test]
+    fn test_tie() {
+        let res = compute_result(&[40, 40, 20], 100, &rules(0, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::Tie);
+        assert_eq!(res.winning_choice, None);
+
+        // A tie for second place doesn't matter
+        let res = compute_result(&[50, 20, 20], 100, &rules(0, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+
+        // A later choice overtaking an earlier tie clears it
+        let res = compute_result(&[20, 20, 60], 100, &rules(0, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(2));
+
+        // Ties with the abstain choice are ignored
+        let with_abstain = TallyRules {
+            abstain_choice: Some(1),
+            ..rules(0, 0)
+        };
+        let res = compute_result(&[30, 30, 10], 100, &with_abstain).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+    }
+
+    #[test]
+    fn test_quorum_checked_before_tie() {
+        let res = compute_result(&[10, 10], 100, &rules(5000, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn test_nothing_staked() {
+        // Used to panic on division by zero
+        let res = compute_result(&[0, 0], 0, &rules(3300, 5000)).unwrap();
+        assert_eq!(res.participation, 0);
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+
+        let exclusive = TallyRules {
+            quorum_comparison: Comparison::Exclusive,
+            ..rules(0, 0)
+        };
+        let res = compute_result(&[0, 0], 0, &exclusive).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn test_nothing_staked_inclusive_zero_quorum() {
+        // 0 >= 0 would meet the quorum, but there's no stake to measure participation against
+        let res = compute_result(&[0, 0], 0, &rules(0, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+        assert!(!res.quorum_met());
+
+        // Votes cast by stakers who have all withdrawn since
+        let res = compute_result(&[10, 0], 0, &rules(0, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+        assert_eq!(res.winning_choice, None);
+    }
+
+    #[test]
+    fn test_no_votes() {
+        let res = compute_result(&[0, 0], 100, &rules(0, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::Rejected);
+        assert_eq!(res.total_votes, 0);
+
+        let res = compute_result(&[0, 0], 100, &rules(1, 0)).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn test_participation_capped() {
+        // Stakers withdrew after voting
+        let res = compute_result(&[150, 0], 100, &rules(10_000, 0)).unwrap();
+        assert_eq!(res.participation, 10_000);
+        assert_eq!(res.outcome, Outcome::Passed);
+    }
+
+    #[test]
+    fn test_full_threshold() {
+        let unanimous = TallyRules {
+            threshold_comparison: Comparison::Inclusive,
+            ..rules(0, 10_000)
+        };
+        let res = compute_result(&[100, 0], 100, &unanimous).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+
+        let res = compute_result(&[99, 1], 100, &unanimous).unwrap();
+        assert_eq!(res.outcome, Outcome::Rejected);
+    }
+
+    #[test]
+    fn test_veto() {
+        let with_veto = TallyRules {
+            abstain_choice: Some(2),
+            veto_choice: Some(3),
+            veto_threshold: 3340,
+            ..rules(0, 4000)
+        };
+
+        // Exactly 33.4% of all votes (abstain included) isn't enough to veto
+        let res = compute_result(&[400, 100, 166, 334], 1000, &with_veto).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+
+        let res = compute_result(&[400, 100, 165, 335], 1000, &with_veto).unwrap();
+        assert_eq!(res.outcome, Outcome::Vetoed);
+        assert_eq!(res.winning_choice, None);
+
+        // Veto votes count against the threshold, but can't win
+        let res = compute_result(
+            &[30, 0, 0, 25],
+            100,
+            &TallyRules {
+                veto_threshold: 5000,
+                min_threshold: 5000,
+                ..with_veto
+            },
+        )
+        .unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        assert_eq!(res.winning_choice, Some(0));
+
+        let res = compute_result(
+            &[30, 10, 0, 30],
+            100,
+            &TallyRules {
+                veto_threshold: 5000,
+                min_threshold: 5000,
+                ..with_veto
+            },
+        )
+        .unwrap();
+        assert_eq!(res.outcome, Outcome::Rejected);
+
+        // Nobody vetoes without veto votes, even with a zero threshold
+        let zero_threshold = TallyRules {
+            veto_threshold: 0,
+            ..with_veto
+        };
+        let res = compute_result(&[10, 0, 0, 0], 100, &zero_threshold).unwrap();
+        assert_eq!(res.outcome, Outcome::Passed);
+        let res = compute_result(&[10, 0, 0, 1], 100, &zero_threshold).unwrap();
+        assert_eq!(res.outcome, Outcome::Vetoed);
+    }
+
+    #[test]
+    fn test_quorum_checked_before_veto() {
+        let with_veto = TallyRules {
+            veto_choice: Some(1),
+            ..rules(5000, 0)
+        };
+        let res = compute_result(&[0, 10], 100, &with_veto).unwrap();
+        assert_eq!(res.outcome, Outcome::QuorumNotMet);
+    }
+
+    #[test]
+    fn test_percentages() {
+        let res = compute_result(&[1, 1, 1], 10, &rules(0, 0)).unwrap();
+        assert_eq!(res.percentages, vec![3333, 3333, 3333]);
+        assert_eq!(res.participation, 3000);
+        assert_eq!(res.total_staked, 10);
+
+        let res = compute_result(&[0, 0], 10, &rules(0, 0)).unwrap();
+        assert_eq!(res.percentages, vec![0, 0]);
+
+        let res = compute_result(&[3, 1], 4, &rules(0, 0)).unwrap();
+        assert_eq!(res.percentages, vec![7500, 2500]);
+        assert_eq!(res.participation, 10_000);
+    }
+
+    #[test]
+    fn test_overflow() {
+        let err = compute_result(&[u128::MAX, 1], 100, &rules(0, 0)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::TallyOverflow));
+
+        let err = compute_result(&[u128::MAX / 2, 0], u128::MAX, &rules(0, 0)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::TallyOverflow));
+    }
+
+    #[test]
+    fn test_empty_tally() {
+        let err = compute_result(&[], 100, &rules(0, 0)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::StorageCorrupted));
+    }
+}
@@ -0,0 +1,796 @@
+use crate::msg::{HandleMsg, InitMsg, QueryAnswer, QueryMsg, ReceiveMsg, ResponseStatus};
+use crate::state::{
+    is_key_valid, read_lock, round_to_week, set_viewing_key, store_lock, total_voting_power,
+    voting_power, Config, Lock, ADMIN_KEY, CLOCK_KEY, CONFIG_KEY, SUBSCRIBERS_KEY, WEEK,
+};
+use cosmwasm_std::{
+    from_binary, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
+    InitResponse, Querier, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use scrt_finance::errors::ContractError;
+use scrt_finance::events;
+use scrt_finance::lp_staking_msg::LPStakingHookMsg;
+use scrt_finance::secret_vote_types::DEFAULT_RESPONSE_BLOCK_SIZE;
+use scrt_finance::types::SecretContract;
+use secret_toolkit::snip20;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use secret_toolkit::utils::{pad_handle_result, pad_query_result};
+
+const BLOCK_SIZE: usize = DEFAULT_RESPONSE_BLOCK_SIZE as usize;
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    if msg.max_lock_duration < WEEK {
+        return Err(ContractError::InvalidMaxLockDuration.into());
+    }
+
+    TypedStoreMut::attach(&mut deps.storage).store(ADMIN_KEY, &env.message.sender)?;
+    TypedStoreMut::attach(&mut deps.storage).store(CLOCK_KEY, &env.block.time)?;
+    TypedStoreMut::attach(&mut deps.storage)
+        .store(SUBSCRIBERS_KEY, &Vec::<SecretContract>::new())?;
+    TypedStoreMut::attach(&mut deps.storage).store(
+        CONFIG_KEY,
+        &Config {
+            token: msg.token.clone(),
+            max_lock_duration: msg.max_lock_duration,
+        },
+    )?;
+
+    Ok(InitResponse {
+        messages: vec![snip20::register_receive_msg(
+            env.contract_code_hash,
+            None,
+            BLOCK_SIZE,
+            msg.token.contract_hash,
+            msg.token.address,
+        )?],
+        log: vec![],
+    })
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    // Queries don't get the block time, and can't compute voting power before the latest transaction
+    TypedStoreMut::attach(&mut deps.storage).store(CLOCK_KEY, &env.block.time)?;
+
+    let response = match msg {
+        HandleMsg::Receive {
+            from, amount, msg, ..
+        } => receive(deps, env, from, amount.u128(), msg),
+        HandleMsg::ExtendLock { unlock_time, .. } => extend_lock(deps, env, unlock_time),
+        HandleMsg::Withdraw { .. } => withdraw(deps, env),
+        HandleMsg::SetViewingKey { key, .. } => set_key(deps, env, key),
+        HandleMsg::Checkpoint { stakers } => checkpoint(deps, env, stakers),
+        HandleMsg::LockStake { .. } => stake_lock_response(events::LOCK_STAKE),
+        HandleMsg::UnlockStake { .. } => stake_lock_response(events::UNLOCK_STAKE),
+        HandleMsg::AddSubs { contracts } => add_subscribers(deps, env, contracts),
+        HandleMsg::RemoveSubs { contracts } => remove_subscribers(deps, env, contracts),
+        HandleMsg::ChangeAdmin { new_admin } => change_admin(deps, env, new_admin),
+    };
+
+    pad_handle_result(response, BLOCK_SIZE)
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let clock: u64 = TypedStore::attach(&deps.storage).load(CLOCK_KEY)?;
+    // Locks may have changed since an earlier time
+    let at = |as_of: Option<u64>| as_of.map_or(clock, |as_of| as_of.max(clock));
+
+    let response = match msg {
+        QueryMsg::TotalLocked { as_of } => to_binary(&QueryAnswer::TotalLocked {
+            amount: Uint128(total_voting_power(
+                deps,
+                at(as_of),
+                config.max_lock_duration,
+            )?),
+        }),
+        QueryMsg::Config {} => to_binary(&QueryAnswer::Config {
+            token: config.token,
+            max_lock_duration: config.max_lock_duration,
+            as_of: clock,
+        }),
+        QueryMsg::Balance {
+            address,
+            key,
+            as_of,
+        } => query_balance(deps, &config, at(as_of), address, key),
+        QueryMsg::Lock {
+            address,
+            key,
+            as_of,
+        } => query_lock(deps, &config, at(as_of), address, key),
+    };
+
+    pad_query_result(response, BLOCK_SIZE)
+}
+
+// Handle
+
+fn receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    from: HumanAddr,
+    amount: u128,
+    msg: Binary,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    if env.message.sender != config.token.address {
+        return Err(StdError::unauthorized());
+    }
+    if amount == 0 {
+        return Err(ContractError::NothingToLock.into());
+    }
+
+    match from_binary(&msg)? {
+        ReceiveMsg::CreateLock { unlock_time } => {
+            create_lock(deps, env, &config, from, amount, unlock_time)
+        }
+        ReceiveMsg::IncreaseAmount {} => increase_amount(deps, env, &config, from, amount),
+    }
+}
+
+fn create_lock<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: &Config,
+    staker: HumanAddr,
+    amount: u128,
+    unlock_time: u64,
+) -> StdResult<HandleResponse> {
+    let prev_lock = read_lock(deps, &staker)?;
+    if prev_lock.amount > 0 {
+        if prev_lock.unlock_time <= env.block.time {
+            return Err(ContractError::LockExpired.into());
+        }
+        return Err(ContractError::LockExists.into());
+    }
+
+    let lock = Lock {
+        amount,
+        unlock_time: valid_unlock_time(&env, config, env.block.time, unlock_time)?,
+    };
+    store_lock(deps, &staker, &prev_lock, &lock)?;
+
+    voting_power_response(deps, &env, config, vec![staker], events::CREATE_LOCK)
+}
+
+fn increase_amount<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    config: &Config,
+    staker: HumanAddr,
+    amount: u128,
+) -> StdResult<HandleResponse> {
+    let prev_lock = read_active_lock(deps, &env, &staker)?;
+    let lock = Lock {
+        amount: prev_lock
+            .amount
+            .checked_add(amount)
+            .ok_or_else(|| StdError::from(ContractError::LockOverflow))?,
+        ..prev_lock
+    };
+    store_lock(deps, &staker, &prev_lock, &lock)?;
+
+    voting_power_response(
+        deps,
+        &env,
+        config,
+        vec![staker],
+        events::INCREASE_LOCK_AMOUNT,
+    )
+}
+
+fn extend_lock<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    unlock_time: u64,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let staker = env.message.sender.clone();
+    let prev_lock = read_active_lock(deps, &env, &staker)?;
+    let lock = Lock {
+        unlock_time: valid_unlock_time(&env, &config, prev_lock.unlock_time, unlock_time)?,
+        ..prev_lock
+    };
+    store_lock(deps, &staker, &prev_lock, &lock)?;
+
+    voting_power_response(deps, &env, &config, vec![staker], events::EXTEND_LOCK)
+}
+
+fn withdraw<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+    let staker = env.message.sender.clone();
+    let prev_lock = read_lock(deps, &staker)?;
+    if prev_lock.amount == 0 {
+        return Err(ContractError::NoLock.into());
+    }
+    if prev_lock.unlock_time > env.block.time {
+        return Err(ContractError::LockNotExpired {
+            unlock_time: prev_lock.unlock_time,
+        }
+        .into());
+    }
+    store_lock(deps, &staker, &prev_lock, &Lock::default())?;
+
+    let mut response =
+        voting_power_response(deps, &env, &config, vec![staker.clone()], events::WITHDRAW)?;
+    response.messages.insert(
+        0,
+        snip20::transfer_msg(
+            staker,
+            Uint128(prev_lock.amount),
+            None,
+            BLOCK_SIZE,
+            config.token.contract_hash,
+            config.token.address,
+        )?,
+    );
+
+    Ok(response)
+}
+
+fn set_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    set_viewing_key(deps, &env.message.sender, &key)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+/// Voting power decays without any transaction, so subscribers only see it when someone asks
+fn checkpoint<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    stakers: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let config: Config = TypedStore::attach(&deps.storage).load(CONFIG_KEY)?;
+
+    voting_power_response(deps, &env, &config, stakers, events::CHECKPOINT)
+}
+
+/// Locked tokens can't be withdrawn before they unlock, when they have no voting power left, so
+/// there's nothing to lock for a poll
+fn stake_lock_response(action: &str) -> StdResult<HandleResponse> {
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![events::action(action)],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+fn add_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contracts: Vec<SecretContract>,
+) -> StdResult<HandleResponse> {
+    update_subscribers(deps, env, |subscribers| subscribers.extend(contracts))
+}
+
+fn remove_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contracts: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    update_subscribers(deps, env, |subscribers| {
+        subscribers.retain(|s| !contracts.contains(&s.address))
+    })
+}
+
+fn update_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    update: impl FnOnce(&mut Vec<SecretContract>),
+) -> StdResult<HandleResponse> {
+    enforce_admin(deps, &env)?;
+
+    let mut subscribers_store = TypedStoreMut::attach(&mut deps.storage);
+    let mut subscribers: Vec<SecretContract> = subscribers_store.load(SUBSCRIBERS_KEY)?;
+    update(&mut subscribers);
+    subscribers_store.store(SUBSCRIBERS_KEY, &subscribers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![events::action(events::UPDATE_SUBSCRIBERS)],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+fn change_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    new_admin: HumanAddr,
+) -> StdResult<HandleResponse> {
+    enforce_admin(deps, &env)?;
+    TypedStoreMut::attach(&mut deps.storage).store(ADMIN_KEY, &new_admin)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![events::action(events::CHANGE_ADMIN)],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+// Query
+
+fn query_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    config: &Config,
+    now: u64,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<Binary> {
+    if !is_key_valid(deps, &address, &key)? {
+        return viewing_key_error();
+    }
+
+    let lock = read_lock(deps, &address)?;
+    to_binary(&QueryAnswer::Balance {
+        amount: Uint128(voting_power(
+            lock.amount,
+            lock.unlock_time,
+            now,
+            config.max_lock_duration,
+        )?),
+    })
+}
+
+fn query_lock<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    config: &Config,
+    now: u64,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<Binary> {
+    if !is_key_valid(deps, &address, &key)? {
+        return viewing_key_error();
+    }
+
+    let lock = read_lock(deps, &address)?;
+    to_binary(&QueryAnswer::Lock {
+        amount: Uint128(lock.amount),
+        unlock_time: lock.unlock_time,
+        voting_power: Uint128(voting_power(
+            lock.amount,
+            lock.unlock_time,
+            now,
+            config.max_lock_duration,
+        )?),
+    })
+}
+
+/// Same as a SNIP-20 token, this is an answer rather than an error
+fn viewing_key_error() -> StdResult<Binary> {
+    to_binary(&QueryAnswer::ViewingKeyError {
+        msg: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+// Helpers
+
+fn enforce_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<()> {
+    let admin: HumanAddr = TypedStore::attach(&deps.storage).load(ADMIN_KEY)?;
+    if env.message.sender != admin {
+        return Err(StdError::unauthorized());
+    }
+
+    Ok(())
+}
+
+fn read_active_lock<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    staker: &HumanAddr,
+) -> StdResult<Lock> {
+    let lock = read_lock(deps, staker)?;
+    if lock.amount == 0 {
+        return Err(ContractError::NoLock.into());
+    }
+    if lock.unlock_time <= env.block.time {
+        return Err(ContractError::LockExpired.into());
+    }
+
+    Ok(lock)
+}
+
+/// Rounds the unlock time down to a whole week, which has to be after `min`
+fn valid_unlock_time(env: &Env, config: &Config, min: u64, unlock_time: u64) -> StdResult<u64> {
+    let rounded = round_to_week(unlock_time);
+    let max = env.block.time + config.max_lock_duration;
+    if rounded <= min || rounded > max {
+        return Err(ContractError::InvalidUnlockTime {
+            min,
+            max,
+            unlock_time: rounded,
+        }
+        .into());
+    }
+
+    Ok(rounded)
+}
+
+/// Pushes the stakers' current voting power to every subscriber, i.e. the poll factory.
+/// Same as the staking pool, the stakers aren't logged
+fn voting_power_response<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+    config: &Config,
+    stakers: Vec<HumanAddr>,
+    action: &str,
+) -> StdResult<HandleResponse> {
+    let subscribers: Vec<SecretContract> =
+        TypedStore::attach(&deps.storage).load(SUBSCRIBERS_KEY)?;
+
+    let mut messages = vec![];
+    for staker in stakers {
+        let lock = read_lock(deps, &staker)?;
+        let msg = to_binary(&LPStakingHookMsg::UpdateVotingPower {
+            voter: staker,
+            new_power: Uint128(voting_power(
+                lock.amount,
+                lock.unlock_time,
+                env.block.time,
+                config.max_lock_duration,
+            )?),
+            staked_since: None, // Lock-based power already depends on time
+        })?;
+        messages.extend(subscribers.iter().map(|s| {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: s.address.clone(),
+                callback_code_hash: s.contract_hash.clone(),
+                msg: msg.clone(),
+                send: vec![],
+            })
+        }));
+    }
+
+    Ok(HandleResponse {
+        messages,
+        log: vec![events::action(action)],
+        data: Some(to_binary(&ResponseStatus::Success)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{mock_dependencies, MockApi, MockQuerier, MockStorage};
+    use cosmwasm_std::{log, BlockInfo, ContractInfo, LogAttribute, MessageInfo};
+
+    const MAX_LOCK: u64 = 4 * WEEK;
+
+    fn mock_env<U: Into<HumanAddr>>(sender: U, time: u64) -> Env {
+        Env {
+            block: BlockInfo {
+                height: time / 5,
+                time,
+                chain_id: "cosmos-testnet-14002".to_string(),
+            },
+            message: MessageInfo {
+                sender: sender.into(),
+                sent_funds: vec![],
+            },
+            contract: ContractInfo {
+                address: HumanAddr::from("escrow"),
+            },
+            contract_key: Some("".to_string()),
+            contract_code_hash: "".to_string(),
+        }
+    }
+
+    fn init_helper() -> Extern<MockStorage, MockApi, MockQuerier> {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = InitMsg {
+            token: SecretContract {
+                address: HumanAddr("sefi".to_string()),
+                contract_hash: "".to_string(),
+            },
+            max_lock_duration: MAX_LOCK,
+        };
+        init(&mut deps, mock_env("admin", 0), msg).unwrap();
+
+        deps
+    }
+
+    fn receive_msg(staker: &str, amount: u128, msg: ReceiveMsg) -> HandleMsg {
+        HandleMsg::Receive {
+            sender: HumanAddr(staker.to_string()),
+            from: HumanAddr(staker.to_string()),
+            amount: Uint128(amount),
+            msg: to_binary(&msg).unwrap(),
+        }
+    }
+
+    fn lock(
+        deps: &mut Extern<MockStorage, MockApi, MockQuerier>,
+        staker: &str,
+        amount: u128,
+        unlock_time: u64,
+        time: u64,
+    ) -> StdResult<HandleResponse> {
+        let msg = receive_msg(staker, amount, ReceiveMsg::CreateLock { unlock_time });
+        handle(deps, mock_env("sefi", time), msg)
+    }
+
+    fn query_power(
+        deps: &Extern<MockStorage, MockApi, MockQuerier>,
+        staker: &str,
+        as_of: Option<u64>,
+    ) -> u128 {
+        let msg = QueryMsg::Balance {
+            address: HumanAddr(staker.to_string()),
+            key: "key".to_string(),
+            as_of,
+        };
+        match from_binary(&query(deps, msg).unwrap()).unwrap() {
+            QueryAnswer::Balance { amount } => amount.u128(),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    fn query_total(deps: &Extern<MockStorage, MockApi, MockQuerier>, as_of: Option<u64>) -> u128 {
+        match from_binary(&query(deps, QueryMsg::TotalLocked { as_of }).unwrap()).unwrap() {
+            QueryAnswer::TotalLocked { amount } => amount.u128(),
+            _ => panic!("unexpected answer"),
+        }
+    }
+
+    fn set_key(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, staker: &str, time: u64) {
+        let msg = HandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        };
+        handle(deps, mock_env(staker, time), msg).unwrap();
+    }
+
+    /// Log attributes are padded along with the rest of the response
+    fn unpadded_log(res: &HandleResponse) -> Vec<LogAttribute> {
+        res.log
+            .iter()
+            .map(|attr| log(attr.key.trim_end(), attr.value.trim_end()))
+            .collect()
+    }
+
+    #[test]
+    fn test_init() {
+        let mut deps = mock_dependencies(20, &[]);
+        let msg = |max_lock_duration: u64| InitMsg {
+            token: SecretContract {
+                address: HumanAddr("sefi".to_string()),
+                contract_hash: "".to_string(),
+            },
+            max_lock_duration,
+        };
+        let err = init(&mut deps, mock_env("admin", 0), msg(WEEK - 1)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::InvalidMaxLockDuration));
+
+        // Registers with the token to receive deposits
+        let res = init(&mut deps, mock_env("admin", 0), msg(WEEK)).unwrap();
+        assert_eq!(res.messages.len(), 1);
+    }
+
+    #[test]
+    fn test_lock_lifecycle() {
+        let mut deps = init_helper();
+        set_key(&mut deps, "alice", 0);
+
+        let msg = receive_msg("alice", 1000, ReceiveMsg::CreateLock { unlock_time: 0 });
+        let err = handle(&mut deps, mock_env("not sefi", 10 * WEEK), msg).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+        let err = lock(&mut deps, "alice", 0, 12 * WEEK, 10 * WEEK).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::NothingToLock));
+
+        // Rounded down to 12 weeks, half the maximum lock
+        lock(&mut deps, "alice", 1000, 12 * WEEK + 5, 10 * WEEK).unwrap();
+        assert_eq!(query_power(&deps, "alice", None), 500);
+        assert_eq!(query_total(&deps, None), 500);
+        let err = lock(&mut deps, "alice", 1000, 13 * WEEK, 10 * WEEK).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::LockExists));
+
+        let msg = receive_msg("alice", 1000, ReceiveMsg::IncreaseAmount {});
+        handle(&mut deps, mock_env("sefi", 11 * WEEK), msg).unwrap();
+        assert_eq!(query_power(&deps, "alice", None), 500);
+
+        let extend = |unlock_time: u64| HandleMsg::ExtendLock {
+            unlock_time,
+            padding: None,
+        };
+        handle(&mut deps, mock_env("alice", 11 * WEEK), extend(14 * WEEK)).unwrap();
+        assert_eq!(query_power(&deps, "alice", None), 1500);
+        assert_eq!(query_total(&deps, None), 1500);
+        let err = handle(&mut deps, mock_env("alice", 11 * WEEK), extend(16 * WEEK)).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::InvalidUnlockTime {
+                min: 14 * WEEK,
+                max: 15 * WEEK,
+                unlock_time: 16 * WEEK,
+            })
+        );
+
+        let withdraw = HandleMsg::Withdraw { padding: None };
+        let err = handle(&mut deps, mock_env("alice", 13 * WEEK), withdraw).unwrap_err();
+        assert_eq!(
+            err,
+            StdError::from(ContractError::LockNotExpired {
+                unlock_time: 14 * WEEK
+            })
+        );
+
+        // Decayed to nothing
+        let err = handle(&mut deps, mock_env("alice", 14 * WEEK), extend(15 * WEEK)).unwrap_err();
+        assert_eq!(err, StdError::from(ContractError::LockExpired));
+        assert_eq!(query_power(&deps, "alice", None), 0);
+        assert_eq!(query_total(&deps, None), 0);
+
+        let withdraw = HandleMsg::Withdraw { padding: None };
+        let res = handle(&mut deps, mock_env("alice", 14 * WEEK), withdraw).unwrap();
+        assert_eq!(res.messages.len(), 1); // The transfer back, there are no subscribers
+        assert_eq!(
+            read_lock(&deps, &HumanAddr("alice".into())).unwrap(),
+            Lock::default()
+        );
+
+        // A new lock can be created after withdrawing
+        lock(&mut deps, "alice", 1000, 18 * WEEK, 14 * WEEK).unwrap();
+        assert_eq!(query_power(&deps, "alice", None), 1000);
+    }
+
+    #[test]
+    fn test_lock_overflow() {
+        let mut deps = init_helper();
+        let overflow = StdError::from(ContractError::LockOverflow);
+
+        // The amount fits, but its voting power doesn't
+        let err = lock(&mut deps, "alice", u128::MAX, 14 * WEEK, 10 * WEEK).unwrap_err();
+        assert_eq!(err, overflow);
+
+        // Failed transactions are reverted by the chain, not here, so Bob unlocks in another week
+        lock(&mut deps, "bob", 1000, 13 * WEEK, 10 * WEEK).unwrap();
+        let msg = receive_msg("bob", u128::MAX, ReceiveMsg::IncreaseAmount {});
+        let err = handle(&mut deps, mock_env("sefi", 11 * WEEK), msg).unwrap_err();
+        assert_eq!(err, overflow);
+    }
+
+    #[test]
+    fn test_total_voting_power() {
+        let mut deps = init_helper();
+        lock(&mut deps, "alice", 4000, 14 * WEEK, 10 * WEEK).unwrap();
+        lock(&mut deps, "bob", 2000, 12 * WEEK, 10 * WEEK).unwrap();
+        lock(&mut deps, "carol", 1000, 12 * WEEK, 10 * WEEK).unwrap();
+        assert_eq!(query_total(&deps, None), 4000 + 1000 + 500);
+        // Not before the latest transaction, the locks may have changed since
+        assert_eq!(query_total(&deps, Some(0)), 4000 + 1000 + 500);
+        assert_eq!(query_total(&deps, Some(12 * WEEK)), 2000);
+
+        // Bob's and Carol's locks expire first
+        handle(
+            &mut deps,
+            mock_env("anyone", 12 * WEEK),
+            HandleMsg::Checkpoint { stakers: vec![] },
+        )
+        .unwrap();
+        assert_eq!(query_total(&deps, None), 2000);
+    }
+
+    #[test]
+    fn test_voting_power_updates() {
+        let mut deps = init_helper();
+        let add_subs = || HandleMsg::AddSubs {
+            contracts: vec![SecretContract {
+                address: HumanAddr("factory".to_string()),
+                contract_hash: "".to_string(),
+            }],
+        };
+        let err = handle(&mut deps, mock_env("alice", 0), add_subs()).unwrap_err();
+        assert_eq!(err, StdError::unauthorized());
+        handle(&mut deps, mock_env("admin", 0), add_subs()).unwrap();
+
+        let update = |voter: &str, new_power: u128| {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: HumanAddr("factory".to_string()),
+                callback_code_hash: "".to_string(),
+                msg: to_binary(&LPStakingHookMsg::UpdateVotingPower {
+                    voter: HumanAddr(voter.to_string()),
+                    new_power: Uint128(new_power),
+                    staked_since: None,
+                })
+                .unwrap(),
+                send: vec![],
+            })
+        };
+
+        let res = lock(&mut deps, "alice", 1000, 14 * WEEK, 10 * WEEK).unwrap();
+        assert_eq!(res.messages, vec![update("alice", 1000)]);
+        assert_eq!(
+            unpadded_log(&res),
+            vec![events::action(events::CREATE_LOCK)]
+        );
+
+        // Anyone can push the decayed voting power
+        let checkpoint = HandleMsg::Checkpoint {
+            stakers: vec![HumanAddr("alice".into()), HumanAddr("bob".into())],
+        };
+        let res = handle(&mut deps, mock_env("bob", 12 * WEEK), checkpoint).unwrap();
+        assert_eq!(res.messages, vec![update("alice", 500), update("bob", 0)]);
+        assert_eq!(unpadded_log(&res), vec![events::action(events::CHECKPOINT)]);
+    }
+
+    #[test]
+    fn test_viewing_key_required() {
+        let mut deps = init_helper();
+        lock(&mut deps, "alice", 1000, 14 * WEEK, 10 * WEEK).unwrap();
+
+        let msg = QueryMsg::Balance {
+            address: HumanAddr("alice".to_string()),
+            key: "key".to_string(),
+            as_of: None,
+        };
+        match from_binary(&query(&deps, msg).unwrap()).unwrap() {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("unexpected answer"),
+        }
+
+        set_key(&mut deps, "alice", 10 * WEEK);
+        let msg = QueryMsg::Lock {
+            address: HumanAddr("alice".to_string()),
+            key: "wrong key".to_string(),
+            as_of: None,
+        };
+        match from_binary(&query(&deps, msg).unwrap()).unwrap() {
+            QueryAnswer::ViewingKeyError { .. } => {}
+            _ => panic!("unexpected answer"),
+        }
+        assert_eq!(query_power(&deps, "alice", None), 1000);
+        assert_eq!(query_power(&deps, "alice", Some(12 * WEEK)), 500);
+    }
+
+    #[test]
+    fn test_stake_locks_ignored() {
+        let mut deps = init_helper();
+        lock(&mut deps, "alice", 1000, 14 * WEEK, 10 * WEEK).unwrap();
+
+        let msg = HandleMsg::LockStake {
+            staker: HumanAddr("alice".to_string()),
+            poll: HumanAddr("poll".to_string()),
+            until: 15 * WEEK,
+        };
+        let res = handle(&mut deps, mock_env("factory", 10 * WEEK), msg).unwrap();
+        assert!(res.messages.is_empty());
+        assert_eq!(unpadded_log(&res), vec![events::action(events::LOCK_STAKE)]);
+
+        let msg = HandleMsg::UnlockStake {
+            poll: HumanAddr("poll".to_string()),
+        };
+        let res = handle(&mut deps, mock_env("factory", 11 * WEEK), msg).unwrap();
+        assert_eq!(
+            unpadded_log(&res),
+            vec![events::action(events::UNLOCK_STAKE)]
+        );
+
+        // Withdrawing only depends on the unlock time
+        let withdraw = HandleMsg::Withdraw { padding: None };
+        handle(&mut deps, mock_env("alice", 14 * WEEK), withdraw).unwrap();
+    }
+}
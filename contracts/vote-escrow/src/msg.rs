@@ -0,0 +1,123 @@
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
+use schemars::JsonSchema;
+use scrt_finance::types::SecretContract;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InitMsg {
+    pub token: SecretContract,  // The token being locked, i.e. SEFI
+    pub max_lock_duration: u64, // In seconds. Locking for this long gives one vote per token
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Binary, // ReceiveMsg
+    },
+    ExtendLock {
+        unlock_time: u64, // Linux epoch, rounded down to a whole week
+        padding: Option<String>,
+    },
+    Withdraw {
+        padding: Option<String>, // Withdraws everything once the lock expires
+    },
+    SetViewingKey {
+        key: String,
+        padding: Option<String>,
+    },
+    /// Pushes the stakers' decayed voting power to the subscribers. Anyone can call this
+    Checkpoint {
+        stakers: Vec<HumanAddr>,
+    },
+    /// Sent by the poll factory as it would to a staking pool. Tokens can't be withdrawn before
+    /// they unlock anyway, and have no voting power left by then, so these do nothing
+    LockStake {
+        staker: HumanAddr,
+        poll: HumanAddr,
+        until: u64,
+    },
+    UnlockStake {
+        poll: HumanAddr,
+    },
+
+    // Admin
+    AddSubs {
+        contracts: Vec<SecretContract>,
+    },
+    RemoveSubs {
+        contracts: Vec<HumanAddr>,
+    },
+    ChangeAdmin {
+        new_admin: HumanAddr,
+    },
+}
+
+/// Sent along with tokens
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    CreateLock {
+        unlock_time: u64, // Linux epoch, rounded down to a whole week
+    },
+    IncreaseAmount {},
+}
+
+/// `TotalLocked` and `Balance` serialize the same as the staking pool's, so the escrow can replace
+/// it as the poll factory's voting power source. Queries don't get the block time, so voting power
+/// is computed as of `as_of`, or as of the escrow's latest transaction if that's later or `as_of`
+/// isn't given. Locks may have changed since an earlier time, so it can't be computed for one
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    TotalLocked {
+        as_of: Option<u64>, // Linux epoch
+    }, // Total voting power
+    Config {},
+
+    // Authenticated
+    Balance {
+        address: HumanAddr,
+        key: String,
+        as_of: Option<u64>,
+    }, // Voting power
+    Lock {
+        address: HumanAddr,
+        key: String,
+        as_of: Option<u64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryAnswer {
+    TotalLocked {
+        amount: Uint128,
+    },
+    Config {
+        token: SecretContract,
+        max_lock_duration: u64,
+        as_of: u64, // The time voting power is computed at
+    },
+    Balance {
+        amount: Uint128,
+    },
+    Lock {
+        amount: Uint128,
+        unlock_time: u64,
+        voting_power: Uint128,
+    },
+    ViewingKeyError {
+        msg: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum ResponseStatus {
+    Success,
+    Failure,
+}
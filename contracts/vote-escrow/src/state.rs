@@ -0,0 +1,158 @@
+use cosmwasm_std::{Api, Extern, HumanAddr, Querier, StdError, StdResult, Storage};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use scrt_finance::errors::ContractError;
+use scrt_finance::types::SecretContract;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+pub const ADMIN_KEY: &[u8] = b"admin";
+pub const CONFIG_KEY: &[u8] = b"config";
+pub const CLOCK_KEY: &[u8] = b"clock";
+pub const SUBSCRIBERS_KEY: &[u8] = b"subscribers";
+pub const PREFIX_LOCKS: &[u8] = b"locks";
+pub const PREFIX_UNLOCKS: &[u8] = b"unlocks";
+pub const PREFIX_VIEWING_KEYS: &[u8] = b"viewingkeys";
+
+/// Unlock times are rounded down to a multiple of this, so the total voting power can be summed
+/// over one bucket per week rather than over every lock
+pub const WEEK: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Serialize, Deserialize)]
+pub struct Config {
+    pub token: SecretContract,
+    pub max_lock_duration: u64,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Lock {
+    pub amount: u128,
+    pub unlock_time: u64,
+}
+
+/// Decays linearly from `amount` when locked for `max_lock_duration`, to nothing at `unlock_time`
+pub fn voting_power(
+    amount: u128,
+    unlock_time: u64,
+    now: u64,
+    max_lock_duration: u64,
+) -> StdResult<u128> {
+    if now >= unlock_time {
+        return Ok(0);
+    }
+
+    amount
+        .checked_mul((unlock_time - now) as u128)
+        .map(|power| power / max_lock_duration as u128)
+        .ok_or_else(|| StdError::from(ContractError::LockOverflow))
+}
+
+pub fn round_to_week(time: u64) -> u64 {
+    time / WEEK * WEEK
+}
+
+/// A staker without a lock has an empty one
+pub fn read_lock<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    staker: &HumanAddr,
+) -> StdResult<Lock> {
+    let locks = ReadonlyPrefixedStorage::new(PREFIX_LOCKS, &deps.storage);
+    Ok(TypedStore::attach(&locks)
+        .may_load(staker.0.as_bytes())?
+        .unwrap_or_default())
+}
+
+/// Stores the lock and moves its amount to its unlock time's bucket
+pub fn store_lock<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    staker: &HumanAddr,
+    prev_lock: &Lock,
+    lock: &Lock,
+) -> StdResult<()> {
+    if prev_lock.amount > 0 {
+        update_unlocks(deps, prev_lock.unlock_time, |total| {
+            total
+                .checked_sub(prev_lock.amount)
+                .ok_or_else(|| StdError::from(ContractError::StorageCorrupted))
+        })?;
+    }
+    if lock.amount > 0 {
+        update_unlocks(deps, lock.unlock_time, |total| {
+            total
+                .checked_add(lock.amount)
+                .ok_or_else(|| StdError::from(ContractError::LockOverflow))
+        })?;
+    }
+
+    let mut locks = PrefixedStorage::new(PREFIX_LOCKS, &mut deps.storage);
+    TypedStoreMut::attach(&mut locks).store(staker.0.as_bytes(), lock)
+}
+
+fn update_unlocks<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    unlock_time: u64,
+    update: impl FnOnce(u128) -> StdResult<u128>,
+) -> StdResult<()> {
+    let mut unlocks = PrefixedStorage::new(PREFIX_UNLOCKS, &mut deps.storage);
+    let mut unlocks = TypedStoreMut::attach(&mut unlocks);
+    let key = unlock_time.to_be_bytes();
+    let total: u128 = unlocks.may_load(&key)?.unwrap_or_default();
+    unlocks.store(&key, &update(total)?)
+}
+
+/// Every lock that hasn't expired unlocks within `max_lock_duration` from now
+pub fn total_voting_power<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    now: u64,
+    max_lock_duration: u64,
+) -> StdResult<u128> {
+    let unlocks = ReadonlyPrefixedStorage::new(PREFIX_UNLOCKS, &deps.storage);
+    let unlocks = TypedStore::attach(&unlocks);
+
+    let mut total: u128 = 0;
+    let mut unlock_time = round_to_week(now) + WEEK;
+    while unlock_time <= now + max_lock_duration {
+        let amount: u128 = unlocks
+            .may_load(&unlock_time.to_be_bytes())?
+            .unwrap_or_default();
+        total = voting_power(amount, unlock_time, now, max_lock_duration)?
+            .checked_add(total)
+            .ok_or_else(|| StdError::from(ContractError::LockOverflow))?;
+        unlock_time += WEEK;
+    }
+
+    Ok(total)
+}
+
+pub fn set_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    staker: &HumanAddr,
+    key: &str,
+) -> StdResult<()> {
+    let mut keys = PrefixedStorage::new(PREFIX_VIEWING_KEYS, &mut deps.storage);
+    TypedStoreMut::attach(&mut keys).store(staker.0.as_bytes(), &hash_key(key))
+}
+
+pub fn is_key_valid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    staker: &HumanAddr,
+    key: &str,
+) -> StdResult<bool> {
+    let keys = ReadonlyPrefixedStorage::new(PREFIX_VIEWING_KEYS, &deps.storage);
+    let expected: Option<[u8; 32]> = TypedStore::attach(&keys).may_load(staker.0.as_bytes())?;
+
+    // Hash anyway, so a missing key takes as long to check as a wrong one
+    let given = hash_key(key);
+    Ok(match expected {
+        Some(expected) => bool::from(given[..].ct_eq(&expected[..])),
+        None => false,
+    })
+}
+
+fn hash_key(key: &str) -> [u8; 32] {
+    let mut hash = [0u8; 32];
+    hash.copy_from_slice(Sha256::digest(key.as_bytes()).as_slice());
+    hash
+}
@@ -0,0 +1,220 @@
+//! Replays voter distributions under different poll configs, using the same tally as
+//! secret-poll's `Finalize`, to see how outcomes would change before updating the factory's
+//! default poll config.
+use cosmwasm_std::{StdError, StdResult, Uint128};
+use scrt_finance::errors::ContractError;
+use scrt_finance::secret_vote_types::{Outcome, PollConfig, VotingPowerCap};
+use secret_poll::tally::{compute_result, TallyResult, TallyRules};
+use serde::{Deserialize, Serialize};
+
+pub mod synthetic;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Ballot {
+    pub choice: u8,
+    pub voting_power: Uint128,
+}
+
+/// The votes of a single poll, either historical or synthetic
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Distribution {
+    pub name: String,
+    pub num_choices: u8,
+    pub abstain_choice: Option<u8>,
+    pub veto_choice: Option<u8>,
+    pub total_staked: Uint128,
+    pub ballots: Vec<Ballot>,
+}
+
+impl Distribution {
+    /// Sums the voting power per choice, the way the poll accumulates votes
+    pub fn tally(&self) -> StdResult<Vec<u128>> {
+        self.capped_tally(&VotingPowerCap::Uncapped)
+    }
+
+    /// Same as `tally`, with every ballot capped against the distribution's total staked
+    pub fn capped_tally(&self, cap: &VotingPowerCap) -> StdResult<Vec<u128>> {
+        let mut tally = vec![0u128; self.num_choices as usize];
+        for ballot in &self.ballots {
+            let votes = tally.get_mut(ballot.choice as usize).ok_or_else(|| {
+                StdError::generic_err(format!(
+                    "{}: ballot for choice {}, but the poll has {} choices",
+                    self.name, ballot.choice, self.num_choices
+                ))
+            })?;
+            let voting_power = cap.apply(ballot.voting_power.u128(), self.total_staked.u128())?;
+            *votes = votes
+                .checked_add(voting_power)
+                .ok_or_else(|| StdError::from(ContractError::TallyOverflow))?;
+        }
+
+        Ok(tally)
+    }
+
+    pub fn rules(&self, config: &PollConfig) -> TallyRules {
+        TallyRules {
+            quorum: config.quorum,
+            min_threshold: config.min_threshold,
+            quorum_comparison: config.quorum_comparison,
+            threshold_comparison: config.threshold_comparison,
+            abstain_choice: self.abstain_choice,
+            veto_choice: self.veto_choice,
+            veto_threshold: config.veto_threshold,
+        }
+    }
+
+    pub fn simulate(&self, config: &PollConfig) -> StdResult<TallyResult> {
+        compute_result(
+            &self.capped_tally(&config.power_cap)?,
+            self.total_staked.u128(),
+            &self.rules(config),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct OutcomeCounts {
+    pub passed: usize,
+    pub rejected: usize,
+    pub quorum_not_met: usize,
+    pub tie: usize,
+    pub vetoed: usize,
+}
+
+impl OutcomeCounts {
+    pub fn record(&mut self, outcome: Outcome) {
+        match outcome {
+            Outcome::Passed => self.passed += 1,
+            Outcome::Rejected => self.rejected += 1,
+            Outcome::QuorumNotMet => self.quorum_not_met += 1,
+            Outcome::Tie => self.tie += 1,
+            Outcome::Vetoed => self.vetoed += 1,
+            Outcome::Cancelled => {} // Never produced by the tally
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.passed + self.rejected + self.quorum_not_met + self.tie + self.vetoed
+    }
+
+    /// X/10000 (basis points) of the polls that passed
+    pub fn pass_rate(&self) -> u16 {
+        match self.total() {
+            0 => 0,
+            total => (self.passed * 10_000 / total) as u16,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ConfigReport {
+    pub config: PollConfig,
+    pub counts: OutcomeCounts,
+    pub outcomes: Vec<Outcome>, // Per distribution, in input order
+}
+
+/// Runs every distribution under every config
+pub fn compare(
+    configs: &[PollConfig],
+    distributions: &[Distribution],
+) -> StdResult<Vec<ConfigReport>> {
+    configs
+        .iter()
+        .map(|config| {
+            let mut counts = OutcomeCounts::default();
+            let outcomes = distributions
+                .iter()
+                .map(|d| {
+                    let outcome = d.simulate(config)?.outcome;
+                    counts.record(outcome);
+                    Ok(outcome)
+                })
+                .collect::<StdResult<Vec<Outcome>>>()?;
+
+            Ok(ConfigReport {
+                config: config.clone(),
+                counts,
+                outcomes,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use scrt_finance::secret_vote_types::{Comparison, RevotePolicy, VotingPowerMode};
+
+    fn config(quorum: u16, min_threshold: u16) -> PollConfig {
+        PollConfig {
+            duration: 1000,
+            quorum,
+            min_threshold,
+            quorum_comparison: Comparison::Inclusive,
+            threshold_comparison: Comparison::Exclusive,
+            veto_threshold: 3340,
+            revote_policy: RevotePolicy::Allowed,
+            voting_power_mode: VotingPowerMode::Balance,
+            power_cap: VotingPowerCap::Uncapped,
+            lock_stake: false,
+        }
+    }
+
+    fn distribution(votes: &[(u8, u128)]) -> Distribution {
+        Distribution {
+            name: "test".to_string(),
+            num_choices: 3,
+            abstain_choice: Some(2),
+            veto_choice: None,
+            total_staked: Uint128(1000),
+            ballots: votes
+                .iter()
+                .map(|(choice, power)| Ballot {
+                    choice: *choice,
+                    voting_power: Uint128(*power),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_compare() {
+        let distributions = vec![
+            distribution(&[(0, 200), (1, 100)]),           // 30% turnout
+            distribution(&[(0, 300), (1, 100)]),           // 40% turnout
+            distribution(&[(0, 200), (1, 150), (2, 200)]), // 57% of non-abstain votes
+        ];
+        let reports = compare(&[config(3300, 5000), config(2500, 6000)], &distributions).unwrap();
+
+        assert_eq!(
+            reports[0].outcomes,
+            vec![Outcome::QuorumNotMet, Outcome::Passed, Outcome::Passed]
+        );
+        assert_eq!(reports[0].counts.pass_rate(), 6666);
+        assert_eq!(
+            reports[1].outcomes,
+            vec![Outcome::Passed, Outcome::Passed, Outcome::Rejected]
+        );
+    }
+
+    #[test]
+    fn test_power_cap() {
+        // One whale decides the poll, unless capped at 10% of the 1000 staked
+        let whale = distribution(&[(0, 100), (0, 50), (1, 400)]);
+        let uncapped = config(2000, 5000);
+        let mut capped = config(2000, 5000);
+        capped.power_cap = VotingPowerCap::ShareOfTotal { share: 1000 };
+
+        assert_eq!(whale.simulate(&uncapped).unwrap().winning_choice, Some(1));
+        assert_eq!(
+            whale.capped_tally(&capped.power_cap).unwrap(),
+            vec![150, 100, 0]
+        );
+        assert_eq!(whale.simulate(&capped).unwrap().winning_choice, Some(0));
+    }
+
+    #[test]
+    fn test_invalid_choice() {
+        assert!(distribution(&[(3, 100)]).tally().is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Usage: governance-sim <scenario.json>
+//!
+//! The scenario lists the poll configs to compare, and historical and/or synthetic polls:
+//! ```text
+//! {
+//!   "configs": [{"duration": 86400, "quorum": 3300, "min_threshold": 5000, ...}],
+//!   "distributions": [{"name": "...", "num_choices": 2, "total_staked": "1000", "ballots": [...]}],
+//!   "synthetic": {"seed": 1, "polls": 1000, "stakers": 200, "num_choices": 2, ...}
+//! }
+//! ```
+//! Outcomes are reported per config, along with the polls whose outcome differs from the first
+//! config's.
+use governance_sim::synthetic::{generate, SyntheticParams};
+use governance_sim::{compare, ConfigReport, Distribution};
+use scrt_finance::secret_vote_types::{Comparison, PollConfig};
+use serde::Deserialize;
+use std::{env, fs, process};
+
+#[derive(Deserialize)]
+struct Scenario {
+    configs: Vec<PollConfig>,
+    #[serde(default)]
+    distributions: Vec<Distribution>,
+    synthetic: Option<SyntheticParams>,
+}
+
+fn main() {
+    if let Err(e) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    if args.len() != 1 {
+        return Err("usage: governance-sim <scenario.json>".to_string());
+    }
+
+    let scenario = fs::read(&args[0]).map_err(|e| format!("can't read {}: {}", args[0], e))?;
+    let scenario: Scenario =
+        serde_json::from_slice(&scenario).map_err(|e| format!("invalid scenario: {}", e))?;
+    if scenario.configs.is_empty() {
+        return Err("the scenario has no configs to compare".to_string());
+    }
+
+    let mut distributions = scenario.distributions;
+    if let Some(params) = &scenario.synthetic {
+        distributions.extend(generate(params).map_err(|e| e.to_string())?);
+    }
+
+    let reports = compare(&scenario.configs, &distributions).map_err(|e| e.to_string())?;
+    for (i, report) in reports.iter().enumerate() {
+        print_report(i, report);
+        if i > 0 {
+            print_changes(&reports[0], report, &distributions);
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
+fn print_report(index: usize, report: &ConfigReport) {
+    let config = &report.config;
+    let counts = &report.counts;
+    println!(
+        "config {}: quorum {} ({}), threshold {} ({}), veto threshold {}",
+        index,
+        percent(config.quorum),
+        comparison(config.quorum_comparison),
+        percent(config.min_threshold),
+        comparison(config.threshold_comparison),
+        percent(config.veto_threshold),
+    );
+    println!(
+        "  passed {} ({}), rejected {}, quorum not met {}, tie {}, vetoed {}",
+        counts.passed,
+        percent(counts.pass_rate()),
+        counts.rejected,
+        counts.quorum_not_met,
+        counts.tie,
+        counts.vetoed,
+    );
+}
+
+fn print_changes(baseline: &ConfigReport, report: &ConfigReport, distributions: &[Distribution]) {
+    let changes: Vec<String> = distributions
+        .iter()
+        .zip(baseline.outcomes.iter().zip(report.outcomes.iter()))
+        .filter(|(_, (before, after))| before != after)
+        .map(|(d, (before, after))| format!("    {}: {:?} -> {:?}", d.name, before, after))
+        .collect();
+
+    println!("  {} outcomes differ from config 0", changes.len());
+    for change in changes {
+        println!("{}", change);
+    }
+}
+
+fn comparison(comparison: Comparison) -> &'static str {
+    match comparison {
+        Comparison::Inclusive => "inclusive",
+        Comparison::Exclusive => "exclusive",
+    }
+}
+
+/// Formats X/10000 (basis points) as a percentage
+fn percent(bps: u16) -> String {
+    format!("{}.{:02}%", bps / 100, bps % 100)
+}
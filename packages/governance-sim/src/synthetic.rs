@@ -0,0 +1,128 @@
+//! Deterministic synthetic voter distributions
+use crate::{Ballot, Distribution};
+use cosmwasm_std::{StdError, StdResult, Uint128};
+use rand_chacha::ChaChaRng;
+use rand_core::{RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SyntheticParams {
+    pub seed: u64,
+    pub polls: usize,
+    pub stakers: usize,
+    pub num_choices: u8,
+    pub abstain_choice: Option<u8>,
+    pub veto_choice: Option<u8>,
+    pub total_staked: Uint128,
+    pub min_turnout: u16, // X/10000 (basis points) chance of each staker voting
+    pub max_turnout: u16, // X/10000 (basis points)
+}
+
+/// Generates `params.polls` distributions over the same set of stakers. Each poll draws its own
+/// turnout and preference for every choice, so the same seed always yields the same polls
+pub fn generate(params: &SyntheticParams) -> StdResult<Vec<Distribution>> {
+    if params.num_choices < 2 {
+        return Err(StdError::generic_err(
+            "synthetic polls need at least two choices",
+        ));
+    }
+
+    let mut rng = ChaChaRng::seed_from_u64(params.seed);
+    let stakes = stakes(&mut rng, params.stakers, params.total_staked.u128());
+
+    Ok((0..params.polls)
+        .map(|i| {
+            let turnout = between(&mut rng, params.min_turnout, params.max_turnout);
+            let preferences: Vec<u64> = (0..params.num_choices)
+                .map(|_| 1 + below(&mut rng, 100))
+                .collect();
+            let preference_total: u64 = preferences.iter().sum();
+
+            let mut ballots = vec![];
+            for stake in &stakes {
+                if below(&mut rng, 10_000) < turnout as u64 {
+                    ballots.push(Ballot {
+                        choice: pick(&preferences, below(&mut rng, preference_total)),
+                        voting_power: Uint128(*stake),
+                    });
+                }
+            }
+
+            Distribution {
+                name: format!("synthetic-{}", i),
+                num_choices: params.num_choices,
+                abstain_choice: params.abstain_choice,
+                veto_choice: params.veto_choice,
+                total_staked: params.total_staked,
+                ballots,
+            }
+        })
+        .collect())
+}
+
+/// Splits `total` randomly between `stakers`, so that the stakes add up to exactly `total`
+fn stakes(rng: &mut ChaChaRng, stakers: usize, total: u128) -> Vec<u128> {
+    if stakers == 0 {
+        return vec![];
+    }
+
+    let weights: Vec<u128> = (0..stakers).map(|_| 1 + below(rng, 1000) as u128).collect();
+    let weight_total: u128 = weights.iter().sum();
+    let mut stakes: Vec<u128> = weights.iter().map(|w| total * w / weight_total).collect();
+
+    let remainder = total - stakes.iter().sum::<u128>();
+    stakes[0] += remainder;
+
+    stakes
+}
+
+fn pick(preferences: &[u64], mut roll: u64) -> u8 {
+    for (choice, preference) in preferences.iter().enumerate() {
+        if roll < *preference {
+            return choice as u8;
+        }
+        roll -= preference;
+    }
+
+    (preferences.len() - 1) as u8
+}
+
+fn between(rng: &mut ChaChaRng, min: u16, max: u16) -> u16 {
+    if max <= min {
+        return min;
+    }
+
+    min + below(rng, (max - min) as u64 + 1) as u16
+}
+
+fn below(rng: &mut ChaChaRng, n: u64) -> u64 {
+    rng.next_u64() % n
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deterministic() {
+        let params = SyntheticParams {
+            seed: 7,
+            polls: 5,
+            stakers: 20,
+            num_choices: 3,
+            abstain_choice: Some(2),
+            veto_choice: None,
+            total_staked: Uint128(1_000_000),
+            min_turnout: 2000,
+            max_turnout: 8000,
+        };
+
+        let polls = generate(&params).unwrap();
+        assert_eq!(polls, generate(&params).unwrap());
+        assert_eq!(polls.len(), 5);
+        for poll in &polls {
+            let voted: u128 = poll.tally().unwrap().iter().sum();
+            assert!(voted <= 1_000_000);
+        }
+    }
+}
@@ -0,0 +1,522 @@
+use cosmwasm_std::testing::MockApi;
+use cosmwasm_std::{
+    from_binary, from_slice, to_vec, Binary, BlockInfo, ContractInfo, CosmosMsg, Empty, Env,
+    Extern, HandleResponse, HumanAddr, InitResponse, LogAttribute, MessageInfo, Querier,
+    QuerierResult, QueryRequest, ReadonlyStorage, StdError, StdResult, Storage, SystemError,
+    WasmMsg, WasmQuery,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
+use std::rc::Rc;
+
+pub const CANONICAL_LENGTH: usize = 20;
+
+pub type AppDeps = Extern<AppStorage, MockApi, AppQuerier>;
+
+/// A contract's storage. Unlike `MockStorage` it can be cloned, so that a failed call can be
+/// rolled back
+#[derive(Clone, Default)]
+pub struct AppStorage {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl ReadonlyStorage for AppStorage {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        self.data.get(key).cloned()
+    }
+}
+
+impl Storage for AppStorage {
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.data.insert(key.to_vec(), value.to_vec());
+    }
+
+    fn remove(&mut self, key: &[u8]) {
+        self.data.remove(key);
+    }
+}
+
+/// A contract that can be stored in an `App`. Messages are passed as raw JSON,
+/// exactly as they would arrive on chain
+pub trait Contract {
+    fn init(&self, deps: &mut AppDeps, env: Env, msg: &[u8]) -> StdResult<InitResponse>;
+    fn handle(&self, deps: &mut AppDeps, env: Env, msg: &[u8]) -> StdResult<HandleResponse>;
+    fn query(&self, deps: &AppDeps, msg: &[u8]) -> StdResult<Binary>;
+}
+
+type InitFn<T> = fn(&mut AppDeps, Env, T) -> StdResult<InitResponse>;
+type HandleFn<T> = fn(&mut AppDeps, Env, T) -> StdResult<HandleResponse>;
+type QueryFn<T> = fn(&AppDeps, T) -> StdResult<Binary>;
+
+/// Wraps a contract's `init`, `handle` and `query` entry points
+pub struct ContractWrapper<I, H, Q> {
+    init_fn: InitFn<I>,
+    handle_fn: HandleFn<H>,
+    query_fn: QueryFn<Q>,
+}
+
+impl<I, H, Q> ContractWrapper<I, H, Q> {
+    pub fn new(init_fn: InitFn<I>, handle_fn: HandleFn<H>, query_fn: QueryFn<Q>) -> Self {
+        Self {
+            init_fn,
+            handle_fn,
+            query_fn,
+        }
+    }
+}
+
+impl<I, H, Q> Contract for ContractWrapper<I, H, Q>
+where
+    I: DeserializeOwned,
+    H: DeserializeOwned,
+    Q: DeserializeOwned,
+{
+    fn init(&self, deps: &mut AppDeps, env: Env, msg: &[u8]) -> StdResult<InitResponse> {
+        (self.init_fn)(deps, env, from_slice(msg)?)
+    }
+
+    fn handle(&self, deps: &mut AppDeps, env: Env, msg: &[u8]) -> StdResult<HandleResponse> {
+        (self.handle_fn)(deps, env, from_slice(msg)?)
+    }
+
+    fn query(&self, deps: &AppDeps, msg: &[u8]) -> StdResult<Binary> {
+        (self.query_fn)(deps, from_slice(msg)?)
+    }
+}
+
+struct Code {
+    contract: Rc<dyn Contract>,
+    hash: String,
+}
+
+#[derive(Clone)]
+struct Instance {
+    code_id: u64,
+    storage: Option<AppStorage>, // Taken out while the contract is running
+}
+
+#[derive(Default)]
+struct Registry {
+    codes: Vec<Code>,
+    contracts: BTreeMap<String, Instance>,
+    labels: BTreeSet<String>,
+}
+
+impl Registry {
+    fn code(&self, code_id: u64) -> StdResult<&Code> {
+        code_id
+            .checked_sub(1)
+            .and_then(|idx| self.codes.get(idx as usize))
+            .ok_or_else(|| StdError::generic_err(format!("no code with id {}", code_id)))
+    }
+
+    fn instance(&self, address: &HumanAddr) -> StdResult<&Instance> {
+        self.contracts
+            .get(&address.0)
+            .ok_or_else(|| StdError::generic_err(format!("no contract at {}", address)))
+    }
+
+    fn code_hash_of(&self, address: &HumanAddr) -> StdResult<String> {
+        let code_id = self.instance(address)?.code_id;
+        Ok(self.code(code_id)?.hash.clone())
+    }
+}
+
+/// Routes smart queries to the contracts stored in the `App`
+#[derive(Clone)]
+pub struct AppQuerier {
+    registry: Rc<RefCell<Registry>>,
+}
+
+impl AppQuerier {
+    /// Runs `f` against the contract's code and storage.
+    /// If `code_hash` is set, it has to match the contract's code hash, like on chain
+    fn with_contract<T>(
+        &self,
+        address: &HumanAddr,
+        code_hash: Option<&str>,
+        f: impl FnOnce(&dyn Contract, &mut AppDeps) -> StdResult<T>,
+    ) -> StdResult<T> {
+        let (contract, storage) = {
+            let mut registry = self.registry.borrow_mut();
+            let actual_hash = registry.code_hash_of(address)?;
+            if let Some(given) = code_hash {
+                if given != actual_hash {
+                    return Err(StdError::generic_err(format!(
+                        "code hash mismatch for {}: expected {}, got {}",
+                        address, actual_hash, given
+                    )));
+                }
+            }
+
+            let code_id = registry.instance(address)?.code_id;
+            let contract = registry.code(code_id)?.contract.clone();
+            let storage = registry
+                .contracts
+                .get_mut(&address.0)
+                .and_then(|instance| instance.storage.take())
+                .ok_or_else(|| {
+                    StdError::generic_err(format!("contract {} is already running", address))
+                })?;
+
+            (contract, storage)
+        };
+
+        let mut deps = Extern {
+            storage,
+            api: MockApi::new(CANONICAL_LENGTH),
+            querier: self.clone(),
+        };
+        let result = f(contract.as_ref(), &mut deps);
+
+        if let Some(instance) = self.registry.borrow_mut().contracts.get_mut(&address.0) {
+            instance.storage = Some(deps.storage);
+        }
+
+        result
+    }
+}
+
+impl Querier for AppQuerier {
+    fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+        let request: QueryRequest<Empty> = match from_slice(bin_request) {
+            Ok(request) => request,
+            Err(e) => return Ok(Err(e)),
+        };
+
+        match request {
+            QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr,
+                callback_code_hash,
+                msg,
+            }) => {
+                if !self
+                    .registry
+                    .borrow()
+                    .contracts
+                    .contains_key(&contract_addr.0)
+                {
+                    return Err(SystemError::NoSuchContract {
+                        addr: contract_addr,
+                    });
+                }
+
+                Ok(self.with_contract(
+                    &contract_addr,
+                    Some(&callback_code_hash),
+                    |contract, deps| contract.query(deps, msg.as_slice()),
+                ))
+            }
+            _ => Err(SystemError::UnsupportedRequest {
+                kind: "only smart wasm queries are supported".to_string(),
+            }),
+        }
+    }
+}
+
+/// The logs emitted by a single contract call
+#[derive(Debug, Clone)]
+pub struct ContractLog {
+    pub contract: HumanAddr,
+    pub attributes: Vec<LogAttribute>,
+}
+
+#[derive(Debug, Default)]
+pub struct AppResponse {
+    pub data: Option<Binary>,   // Returned by the called contract only
+    pub logs: Vec<ContractLog>, // In execution order, including sub-messages
+    pub instantiated: Vec<HumanAddr>,
+}
+
+impl AppResponse {
+    /// All attributes logged by `contract` during this call
+    pub fn logs_of(&self, contract: &HumanAddr) -> Vec<LogAttribute> {
+        self.logs
+            .iter()
+            .filter(|l| &l.contract == contract)
+            .flat_map(|l| l.attributes.clone())
+            .collect()
+    }
+}
+
+/// An in-process chain that runs several contracts side by side.
+///
+/// Messages returned by a contract are executed depth-first, with the contract as sender, just
+/// like on chain. If any of them fails, the whole call fails and every contract's storage is
+/// rolled back to what it was before the call.
+pub struct App {
+    registry: Rc<RefCell<Registry>>,
+    block: BlockInfo,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl App {
+    pub fn new() -> Self {
+        Self {
+            registry: Rc::new(RefCell::new(Registry::default())),
+            block: BlockInfo {
+                height: 1,
+                time: 1_600_000_000,
+                chain_id: "secret-testnet".to_string(),
+            },
+        }
+    }
+
+    /// Stores a contract's code and returns its code id
+    pub fn store_code(&mut self, contract: Box<dyn Contract>) -> u64 {
+        let mut registry = self.registry.borrow_mut();
+        let code_id = registry.codes.len() as u64 + 1;
+        registry.codes.push(Code {
+            contract: Rc::from(contract),
+            hash: format!("{:064x}", code_id),
+        });
+
+        code_id
+    }
+
+    pub fn code_hash(&self, code_id: u64) -> StdResult<String> {
+        Ok(self.registry.borrow().code(code_id)?.hash.clone())
+    }
+
+    pub fn contract_code_hash(&self, address: &HumanAddr) -> StdResult<String> {
+        self.registry.borrow().code_hash_of(address)
+    }
+
+    pub fn block(&self) -> &BlockInfo {
+        &self.block
+    }
+
+    /// Moves the chain forward, assuming ~5 second blocks
+    pub fn advance_time(&mut self, seconds: u64) {
+        self.block.time += seconds;
+        self.block.height += 1 + seconds / 5;
+    }
+
+    pub fn instantiate<T: Serialize>(
+        &mut self,
+        code_id: u64,
+        sender: &str,
+        msg: &T,
+        label: &str,
+    ) -> StdResult<(HumanAddr, AppResponse)> {
+        let msg = to_vec(msg)?;
+        let mut response = AppResponse::default();
+        let address = self.transaction(|app| {
+            app.run_instantiate(
+                HumanAddr(sender.to_string()),
+                code_id,
+                None,
+                &msg,
+                label.to_string(),
+                &mut response,
+            )
+        })?;
+
+        Ok((address, response))
+    }
+
+    pub fn execute<T: Serialize>(
+        &mut self,
+        contract: &HumanAddr,
+        sender: &str,
+        msg: &T,
+    ) -> StdResult<AppResponse> {
+        let msg = to_vec(msg)?;
+        let mut response = AppResponse::default();
+        let data = self.transaction(|app| {
+            app.run_execute(
+                HumanAddr(sender.to_string()),
+                contract,
+                None,
+                &msg,
+                &mut response,
+            )
+        })?;
+
+        Ok(AppResponse { data, ..response })
+    }
+
+    pub fn query<T: Serialize, U: DeserializeOwned>(
+        &self,
+        contract: &HumanAddr,
+        msg: &T,
+    ) -> StdResult<U> {
+        let msg = to_vec(msg)?;
+        let answer = self
+            .querier()
+            .with_contract(contract, None, |c, deps| c.query(deps, &msg))?;
+
+        from_binary(&answer)
+    }
+
+    /// Runs `f`, undoing all of its contract instantiations and storage changes if it fails
+    fn transaction<T>(&mut self, f: impl FnOnce(&mut Self) -> StdResult<T>) -> StdResult<T> {
+        let (contracts, labels) = {
+            let registry = self.registry.borrow();
+            (registry.contracts.clone(), registry.labels.clone())
+        };
+
+        let result = f(self);
+        if result.is_err() {
+            let mut registry = self.registry.borrow_mut();
+            registry.contracts = contracts;
+            registry.labels = labels;
+        }
+
+        result
+    }
+
+    fn querier(&self) -> AppQuerier {
+        AppQuerier {
+            registry: self.registry.clone(),
+        }
+    }
+
+    fn env(&self, contract: &HumanAddr, sender: HumanAddr) -> StdResult<Env> {
+        Ok(Env {
+            block: self.block.clone(),
+            message: MessageInfo {
+                sender,
+                sent_funds: vec![],
+            },
+            contract: ContractInfo {
+                address: contract.clone(),
+            },
+            contract_key: Some("".to_string()),
+            contract_code_hash: self.contract_code_hash(contract)?,
+        })
+    }
+
+    fn run_instantiate(
+        &mut self,
+        sender: HumanAddr,
+        code_id: u64,
+        code_hash: Option<&str>,
+        msg: &[u8],
+        label: String,
+        response: &mut AppResponse,
+    ) -> StdResult<HumanAddr> {
+        let address = {
+            let mut registry = self.registry.borrow_mut();
+            let actual_hash = registry.code(code_id)?.hash.clone();
+            if let Some(given) = code_hash {
+                if given != actual_hash {
+                    return Err(StdError::generic_err(format!(
+                        "code hash mismatch for code id {}: expected {}, got {}",
+                        code_id, actual_hash, given
+                    )));
+                }
+            }
+            if !registry.labels.insert(label.clone()) {
+                return Err(StdError::generic_err(format!(
+                    "label {} is already taken",
+                    label
+                )));
+            }
+
+            let address = HumanAddr(format!("contract{}", registry.contracts.len() + 1));
+            registry.contracts.insert(
+                address.0.clone(),
+                Instance {
+                    code_id,
+                    storage: Some(AppStorage::default()),
+                },
+            );
+
+            address
+        };
+
+        let env = self.env(&address, sender)?;
+        let res = self
+            .querier()
+            .with_contract(&address, None, |c, deps| c.init(deps, env, msg))?;
+
+        response.instantiated.push(address.clone());
+        response.logs.push(ContractLog {
+            contract: address.clone(),
+            attributes: res.log,
+        });
+        self.dispatch(&address, res.messages, response)?;
+
+        Ok(address)
+    }
+
+    fn run_execute(
+        &mut self,
+        sender: HumanAddr,
+        contract: &HumanAddr,
+        code_hash: Option<&str>,
+        msg: &[u8],
+        response: &mut AppResponse,
+    ) -> StdResult<Option<Binary>> {
+        let env = self.env(contract, sender)?;
+        let res = self
+            .querier()
+            .with_contract(contract, code_hash, |c, deps| c.handle(deps, env, msg))?;
+
+        response.logs.push(ContractLog {
+            contract: contract.clone(),
+            attributes: res.log,
+        });
+        self.dispatch(contract, res.messages, response)?;
+
+        Ok(res.data)
+    }
+
+    fn dispatch(
+        &mut self,
+        sender: &HumanAddr,
+        messages: Vec<CosmosMsg>,
+        response: &mut AppResponse,
+    ) -> StdResult<()> {
+        for msg in messages {
+            match msg {
+                CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr,
+                    callback_code_hash,
+                    msg,
+                    ..
+                }) => {
+                    self.run_execute(
+                        sender.clone(),
+                        &contract_addr,
+                        Some(&callback_code_hash),
+                        msg.as_slice(),
+                        response,
+                    )?;
+                }
+                CosmosMsg::Wasm(WasmMsg::Instantiate {
+                    code_id,
+                    callback_code_hash,
+                    msg,
+                    label,
+                    ..
+                }) => {
+                    self.run_instantiate(
+                        sender.clone(),
+                        code_id,
+                        Some(&callback_code_hash),
+                        msg.as_slice(),
+                        label,
+                        response,
+                    )?;
+                }
+                other => {
+                    return Err(StdError::generic_err(format!(
+                        "unsupported message: {:?}",
+                        other
+                    )))
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
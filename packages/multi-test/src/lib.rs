@@ -0,0 +1,50 @@
+//! In-process multi-contract testing for the governance contracts.
+//!
+//! `App` stores contract code, instantiates contracts and routes the `WasmMsg`s and smart queries
+//! they produce, so that flows spanning the factory, its polls and the staking pool or vote escrow
+//! can be tested with a plain `cargo test`.
+pub mod app;
+pub mod mock_staking_pool;
+pub mod mock_token;
+
+pub use app::{App, AppResponse, Contract, ContractLog, ContractWrapper};
+
+pub fn poll_factory_contract() -> Box<dyn Contract> {
+    Box::new(ContractWrapper::new(
+        poll_factory::contract::init,
+        poll_factory::contract::handle,
+        poll_factory::contract::query,
+    ))
+}
+
+pub fn secret_poll_contract() -> Box<dyn Contract> {
+    Box::new(ContractWrapper::new(
+        secret_poll::contract::init,
+        secret_poll::contract::handle,
+        secret_poll::contract::query,
+    ))
+}
+
+pub fn mock_staking_pool_contract() -> Box<dyn Contract> {
+    Box::new(ContractWrapper::new(
+        mock_staking_pool::init,
+        mock_staking_pool::handle,
+        mock_staking_pool::query,
+    ))
+}
+
+pub fn vote_escrow_contract() -> Box<dyn Contract> {
+    Box::new(ContractWrapper::new(
+        vote_escrow::contract::init,
+        vote_escrow::contract::handle,
+        vote_escrow::contract::query,
+    ))
+}
+
+pub fn mock_token_contract() -> Box<dyn Contract> {
+    Box::new(ContractWrapper::new(
+        mock_token::init,
+        mock_token::handle,
+        mock_token::query,
+    ))
+}
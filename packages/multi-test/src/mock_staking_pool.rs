@@ -0,0 +1,372 @@
+//! A stand-in for the SEFI staking pool, implementing only the parts the voting contracts use:
+//! viewing-key protected SNIP-20 balances, stake ages, `TotalLocked`, voting power updates
+//! pushed to subscribers (i.e. the poll factory) on every deposit and redeem, and stake locks
+//! requested by subscribers while a staker's vote is active
+use cosmwasm_std::{
+    from_binary, log, to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr,
+    InitResponse, Querier, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use scrt_finance::lp_staking_msg::{
+    LPStakingHandleMsg, LPStakingHookMsg, LPStakingQueryAnswer, LPStakingQueryMsg,
+    LPStakingReceiveMsg,
+};
+use scrt_finance::types::SecretContract;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+pub const ADMIN_KEY: &[u8] = b"admin";
+pub const TOTAL_LOCKED_KEY: &[u8] = b"totallocked";
+pub const SUBSCRIBERS_KEY: &[u8] = b"subscribers";
+pub const PREFIX_BALANCES: &[u8] = b"balances";
+pub const PREFIX_VIEWING_KEYS: &[u8] = b"viewingkeys";
+pub const PREFIX_STAKED_SINCE: &[u8] = b"stakedsince";
+pub const PREFIX_STAKE_LOCKS: &[u8] = b"stakelocks";
+pub const PREFIX_UNLOCKED_POLLS: &[u8] = b"unlockedpolls";
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InitMsg {}
+
+/// Keeps a staker from redeeming until `until`, unless `poll` is unlocked first
+#[derive(Serialize, Deserialize)]
+struct StakeLock {
+    poll: HumanAddr,
+    until: u64,
+}
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    _msg: InitMsg,
+) -> StdResult<InitResponse> {
+    TypedStoreMut::attach(&mut deps.storage).store(ADMIN_KEY, &env.message.sender)?;
+    TypedStoreMut::attach(&mut deps.storage).store(TOTAL_LOCKED_KEY, &0u128)?;
+    TypedStoreMut::attach(&mut deps.storage)
+        .store(SUBSCRIBERS_KEY, &Vec::<SecretContract>::new())?;
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: LPStakingHandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        // Only the incentivized token can deposit to the real pool. Here deposits are taken on faith
+        LPStakingHandleMsg::Receive {
+            from, amount, msg, ..
+        } => match from_binary(&msg)? {
+            LPStakingReceiveMsg::Deposit {} => deposit(deps, env, from, amount.u128()),
+        },
+        LPStakingHandleMsg::Redeem { amount } => redeem(deps, env, amount),
+        LPStakingHandleMsg::SetViewingKey { key, .. } => set_viewing_key(deps, env, key),
+        LPStakingHandleMsg::CreateViewingKey { .. } => Err(StdError::generic_err(
+            "the mock staking pool only supports SetViewingKey",
+        )),
+        LPStakingHandleMsg::LockStake {
+            staker,
+            poll,
+            until,
+        } => lock_stake(deps, env, staker, poll, until),
+        LPStakingHandleMsg::UnlockStake { poll } => unlock_stake(deps, env, poll),
+        LPStakingHandleMsg::AddSubs { contracts } => add_subscribers(deps, env, contracts),
+        LPStakingHandleMsg::RemoveSubs { contracts } => remove_subscribers(deps, env, contracts),
+    }
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: LPStakingQueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        LPStakingQueryMsg::Balance { address, key, .. } => query_balance(deps, address, key),
+        LPStakingQueryMsg::StakeInfo { address, key } => query_stake_info(deps, address, key),
+        LPStakingQueryMsg::TotalLocked { .. } => to_binary(&LPStakingQueryAnswer::TotalLocked {
+            amount: Uint128(TypedStore::attach(&deps.storage).load(TOTAL_LOCKED_KEY)?),
+        }),
+    }
+}
+
+fn deposit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    staker: HumanAddr,
+    amount: u128,
+) -> StdResult<HandleResponse> {
+    // Would leave the stake's age undefined if the staker has nothing staked yet
+    if amount == 0 {
+        return Err(StdError::generic_err("deposit amount must be positive"));
+    }
+
+    let prev_balance = balance(deps, &staker)?;
+    let new_balance = prev_balance + amount;
+    let total: u128 = TypedStore::attach(&deps.storage).load(TOTAL_LOCKED_KEY)?;
+    TypedStoreMut::attach(&mut deps.storage).store(TOTAL_LOCKED_KEY, &(total + amount))?;
+    set_balance(deps, &staker, new_balance)?;
+
+    // The stake's age is the balance-weighted average of its deposit times
+    let prev_since = staked_since(deps, &staker)? as u128;
+    let since = (prev_balance * prev_since + amount * env.block.time as u128) / new_balance;
+    let mut stake_ages = PrefixedStorage::new(PREFIX_STAKED_SINCE, &mut deps.storage);
+    TypedStoreMut::attach(&mut stake_ages).store(staker.0.as_bytes(), &(since as u64))?;
+
+    Ok(HandleResponse {
+        messages: update_voting_power_msgs(deps, staker, new_balance)?,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn redeem<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Option<Uint128>,
+) -> StdResult<HandleResponse> {
+    let staker = env.message.sender;
+    let locks = active_locks(deps, &staker, env.block.time)?;
+    if let Some(until) = locks.iter().map(|lock| lock.until).max() {
+        return Err(StdError::generic_err(format!(
+            "stake is locked until {}",
+            until
+        )));
+    }
+
+    let prev_balance = balance(deps, &staker)?;
+    let amount = amount.map_or(prev_balance, |a| a.u128());
+    let new_balance = prev_balance
+        .checked_sub(amount)
+        .ok_or_else(|| StdError::generic_err("insufficient funds to redeem"))?;
+    let total: u128 = TypedStore::attach(&deps.storage).load(TOTAL_LOCKED_KEY)?;
+    TypedStoreMut::attach(&mut deps.storage).store(TOTAL_LOCKED_KEY, &(total - amount))?;
+    set_balance(deps, &staker, new_balance)?;
+
+    Ok(HandleResponse {
+        messages: update_voting_power_msgs(deps, staker, new_balance)?,
+        log: vec![],
+        data: None,
+    })
+}
+
+fn set_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    let mut keys = PrefixedStorage::new(PREFIX_VIEWING_KEYS, &mut deps.storage);
+    TypedStoreMut::attach(&mut keys).store(env.message.sender.0.as_bytes(), &key)?;
+
+    Ok(HandleResponse::default())
+}
+
+fn lock_stake<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    staker: HumanAddr,
+    poll: HumanAddr,
+    until: u64,
+) -> StdResult<HandleResponse> {
+    enforce_subscriber(deps, &env)?;
+
+    // A re-vote in the same poll just renews its lock
+    let mut locks = active_locks(deps, &staker, env.block.time)?;
+    locks.retain(|lock| lock.poll != poll);
+    locks.push(StakeLock { poll, until });
+    let mut locks_store = PrefixedStorage::new(PREFIX_STAKE_LOCKS, &mut deps.storage);
+    TypedStoreMut::attach(&mut locks_store).store(staker.0.as_bytes(), &locks)?;
+
+    Ok(HandleResponse::default())
+}
+
+fn unlock_stake<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    poll: HumanAddr,
+) -> StdResult<HandleResponse> {
+    enforce_subscriber(deps, &env)?;
+
+    // A poll may have locked many stakers, so their locks are released lazily
+    let mut unlocked = PrefixedStorage::new(PREFIX_UNLOCKED_POLLS, &mut deps.storage);
+    TypedStoreMut::attach(&mut unlocked).store(poll.0.as_bytes(), &true)?;
+
+    Ok(HandleResponse::default())
+}
+
+fn add_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contracts: Vec<SecretContract>,
+) -> StdResult<HandleResponse> {
+    update_subscribers(deps, env, |subscribers| subscribers.extend(contracts))
+}
+
+fn remove_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    contracts: Vec<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    update_subscribers(deps, env, |subscribers| {
+        subscribers.retain(|s| !contracts.contains(&s.address))
+    })
+}
+
+fn update_subscribers<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    update: impl FnOnce(&mut Vec<SecretContract>),
+) -> StdResult<HandleResponse> {
+    let admin: HumanAddr = TypedStore::attach(&deps.storage).load(ADMIN_KEY)?;
+    if env.message.sender != admin {
+        return Err(StdError::unauthorized());
+    }
+
+    let mut subscribers_store = TypedStoreMut::attach(&mut deps.storage);
+    let mut subscribers: Vec<SecretContract> = subscribers_store.load(SUBSCRIBERS_KEY)?;
+    update(&mut subscribers);
+    subscribers_store.store(SUBSCRIBERS_KEY, &subscribers)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![log("subscribers", subscribers.len())],
+        data: None,
+    })
+}
+
+fn enforce_subscriber<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    env: &Env,
+) -> StdResult<()> {
+    let subscribers: Vec<SecretContract> =
+        TypedStore::attach(&deps.storage).load(SUBSCRIBERS_KEY)?;
+    if !subscribers.iter().any(|s| s.address == env.message.sender) {
+        return Err(StdError::unauthorized());
+    }
+
+    Ok(())
+}
+
+fn query_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<Binary> {
+    if !is_key_valid(deps, &address, key)? {
+        return viewing_key_error();
+    }
+
+    to_binary(&LPStakingQueryAnswer::Balance {
+        amount: Uint128(balance(deps, &address)?),
+    })
+}
+
+fn query_stake_info<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: HumanAddr,
+    key: String,
+) -> StdResult<Binary> {
+    if !is_key_valid(deps, &address, key)? {
+        return viewing_key_error();
+    }
+
+    to_binary(&LPStakingQueryAnswer::StakeInfo {
+        amount: Uint128(balance(deps, &address)?),
+        staked_since: staked_since(deps, &address)?,
+    })
+}
+
+fn is_key_valid<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    key: String,
+) -> StdResult<bool> {
+    let keys = ReadonlyPrefixedStorage::new(PREFIX_VIEWING_KEYS, &deps.storage);
+    let expected_key: Option<String> = TypedStore::attach(&keys).may_load(address.0.as_bytes())?;
+
+    Ok(expected_key == Some(key))
+}
+
+/// Same as a SNIP-20 token, this is an answer rather than an error
+fn viewing_key_error() -> StdResult<Binary> {
+    to_binary(&LPStakingQueryAnswer::ViewingKeyError {
+        msg: "Wrong viewing key for this address or viewing key not set".to_string(),
+    })
+}
+
+fn update_voting_power_msgs<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    voter: HumanAddr,
+    new_power: u128,
+) -> StdResult<Vec<CosmosMsg>> {
+    let subscribers: Vec<SecretContract> =
+        TypedStore::attach(&deps.storage).load(SUBSCRIBERS_KEY)?;
+    let msg = to_binary(&LPStakingHookMsg::UpdateVotingPower {
+        staked_since: Some(staked_since(deps, &voter)?),
+        voter,
+        new_power: Uint128(new_power),
+    })?;
+
+    Ok(subscribers
+        .into_iter()
+        .map(|s| {
+            CosmosMsg::Wasm(WasmMsg::Execute {
+                contract_addr: s.address,
+                callback_code_hash: s.contract_hash,
+                msg: msg.clone(),
+                send: vec![],
+            })
+        })
+        .collect())
+}
+
+/// The staker's locks that haven't expired, and whose polls haven't unlocked them
+fn active_locks<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    staker: &HumanAddr,
+    now: u64,
+) -> StdResult<Vec<StakeLock>> {
+    let locks_store = ReadonlyPrefixedStorage::new(PREFIX_STAKE_LOCKS, &deps.storage);
+    let locks: Vec<StakeLock> = TypedStore::attach(&locks_store)
+        .may_load(staker.0.as_bytes())?
+        .unwrap_or_default();
+
+    let unlocked = ReadonlyPrefixedStorage::new(PREFIX_UNLOCKED_POLLS, &deps.storage);
+    let unlocked = TypedStore::<bool, _>::attach(&unlocked);
+    let mut active = vec![];
+    for lock in locks {
+        if lock.until > now && unlocked.may_load(lock.poll.0.as_bytes())?.is_none() {
+            active.push(lock);
+        }
+    }
+
+    Ok(active)
+}
+
+fn balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<u128> {
+    let balances = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, &deps.storage);
+    Ok(TypedStore::attach(&balances)
+        .may_load(address.0.as_bytes())?
+        .unwrap_or_default())
+}
+
+fn staked_since<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<u64> {
+    let stake_ages = ReadonlyPrefixedStorage::new(PREFIX_STAKED_SINCE, &deps.storage);
+    Ok(TypedStore::attach(&stake_ages)
+        .may_load(address.0.as_bytes())?
+        .unwrap_or_default())
+}
+
+fn set_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    address: &HumanAddr,
+    amount: u128,
+) -> StdResult<()> {
+    let mut balances = PrefixedStorage::new(PREFIX_BALANCES, &mut deps.storage);
+    TypedStoreMut::attach(&mut balances).store(address.0.as_bytes(), &amount)
+}
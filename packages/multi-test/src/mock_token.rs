@@ -0,0 +1,176 @@
+//! A stand-in for the SEFI token, implementing only the parts of SNIP-20 the vote escrow uses:
+//! `RegisterReceive`, `Transfer`, and `Send`, which calls the recipient's `Receive` if it
+//! registered. Balances can be queried without a viewing key
+use cosmwasm_std::{
+    to_binary, Api, Binary, CosmosMsg, Env, Extern, HandleResponse, HumanAddr, InitResponse,
+    Querier, StdError, StdResult, Storage, Uint128, WasmMsg,
+};
+use cosmwasm_storage::{PrefixedStorage, ReadonlyPrefixedStorage};
+use schemars::JsonSchema;
+use secret_toolkit::storage::{TypedStore, TypedStoreMut};
+use serde::{Deserialize, Serialize};
+
+pub const PREFIX_BALANCES: &[u8] = b"balances";
+pub const PREFIX_RECEIVERS: &[u8] = b"receivers";
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InitMsg {
+    pub initial_balances: Vec<InitialBalance>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+pub struct InitialBalance {
+    pub address: HumanAddr,
+    pub amount: Uint128,
+}
+
+/// Padding is accepted and ignored, since serde skips unknown fields
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HandleMsg {
+    RegisterReceive {
+        code_hash: String,
+    },
+    Transfer {
+        recipient: HumanAddr,
+        amount: Uint128,
+    },
+    Send {
+        recipient: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryMsg {
+    Balance { address: HumanAddr },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryAnswer {
+    Balance { amount: Uint128 },
+}
+
+/// What a SNIP-20 token sends to a registered recipient
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum ReceiverHandleMsg {
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+}
+
+pub fn init<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    _env: Env,
+    msg: InitMsg,
+) -> StdResult<InitResponse> {
+    for initial in msg.initial_balances {
+        set_balance(deps, &initial.address, initial.amount.u128())?;
+    }
+
+    Ok(InitResponse::default())
+}
+
+pub fn handle<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    msg: HandleMsg,
+) -> StdResult<HandleResponse> {
+    match msg {
+        HandleMsg::RegisterReceive { code_hash } => {
+            let mut receivers = PrefixedStorage::new(PREFIX_RECEIVERS, &mut deps.storage);
+            TypedStoreMut::attach(&mut receivers)
+                .store(env.message.sender.0.as_bytes(), &code_hash)?;
+
+            Ok(HandleResponse::default())
+        }
+        HandleMsg::Transfer { recipient, amount } => {
+            transfer(deps, &env.message.sender, &recipient, amount.u128())?;
+
+            Ok(HandleResponse::default())
+        }
+        HandleMsg::Send {
+            recipient,
+            amount,
+            msg,
+        } => {
+            let sender = env.message.sender;
+            transfer(deps, &sender, &recipient, amount.u128())?;
+
+            let receivers = ReadonlyPrefixedStorage::new(PREFIX_RECEIVERS, &deps.storage);
+            let code_hash: Option<String> =
+                TypedStore::attach(&receivers).may_load(recipient.0.as_bytes())?;
+            let messages = match code_hash {
+                Some(code_hash) => vec![CosmosMsg::Wasm(WasmMsg::Execute {
+                    contract_addr: recipient,
+                    callback_code_hash: code_hash,
+                    msg: to_binary(&ReceiverHandleMsg::Receive {
+                        sender: sender.clone(),
+                        from: sender,
+                        amount,
+                        msg,
+                    })?,
+                    send: vec![],
+                })],
+                None => vec![],
+            };
+
+            Ok(HandleResponse {
+                messages,
+                log: vec![],
+                data: None,
+            })
+        }
+    }
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Balance { address } => to_binary(&QueryAnswer::Balance {
+            amount: Uint128(balance(deps, &address)?),
+        }),
+    }
+}
+
+fn transfer<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    from: &HumanAddr,
+    to: &HumanAddr,
+    amount: u128,
+) -> StdResult<()> {
+    let from_balance = balance(deps, from)?
+        .checked_sub(amount)
+        .ok_or_else(|| StdError::generic_err("insufficient funds"))?;
+    set_balance(deps, from, from_balance)?;
+    let to_balance = balance(deps, to)? + amount;
+    set_balance(deps, to, to_balance)
+}
+
+fn balance<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+) -> StdResult<u128> {
+    let balances = ReadonlyPrefixedStorage::new(PREFIX_BALANCES, &deps.storage);
+    Ok(TypedStore::attach(&balances)
+        .may_load(address.0.as_bytes())?
+        .unwrap_or_default())
+}
+
+fn set_balance<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    address: &HumanAddr,
+    amount: u128,
+) -> StdResult<()> {
+    let mut balances = PrefixedStorage::new(PREFIX_BALANCES, &mut deps.storage);
+    TypedStoreMut::attach(&mut balances).store(address.0.as_bytes(), &amount)
+}
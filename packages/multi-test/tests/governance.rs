@@ -0,0 +1,753 @@
+use cosmwasm_std::{to_binary, Binary, HumanAddr, StdError, StdResult, Uint128};
+use multi_test::{
+    mock_staking_pool, mock_staking_pool_contract, mock_token, mock_token_contract,
+    poll_factory_contract, secret_poll_contract, vote_escrow_contract, App, AppResponse,
+};
+use poll_factory::msg::{
+    InitMsg as FactoryInitMsg, QueryAnswer as FactoryAnswer, QueryMsg as FactoryQuery,
+};
+use scrt_finance::errors::ErrorCode;
+use scrt_finance::lp_staking_msg::{
+    LPStakingHandleMsg, LPStakingQueryAnswer, LPStakingQueryMsg, LPStakingReceiveMsg,
+};
+use scrt_finance::secret_vote_types::{
+    Comparison, Outcome, PollConfig, PollConfigBounds, PollContract, PollFactoryHandleMsg,
+    PollHandleMsg, PollMetadata, RevealCommittee, RevotePolicy, VotingPowerCap, VotingPowerMode,
+};
+use scrt_finance::types::SecretContract;
+use secret_poll::msg::{QueryAnswer as PollAnswer, QueryMsg as PollQuery};
+use vote_escrow::msg::{
+    HandleMsg as EscrowHandleMsg, InitMsg as EscrowInitMsg, ReceiveMsg as EscrowReceiveMsg,
+};
+use vote_escrow::state::WEEK;
+
+struct Setup {
+    app: App,
+    factory: HumanAddr,
+    pool: HumanAddr,
+}
+
+fn setup() -> Setup {
+    let mut app = App::new();
+    let pool_code = app.store_code(mock_staking_pool_contract());
+    let (pool, _) = app
+        .instantiate(pool_code, "admin", &mock_staking_pool::InitMsg {}, "pool")
+        .unwrap();
+    for (staker, amount) in &[
+        ("alice", 6_000_000),
+        ("bob", 3_000_000),
+        ("carol", 1_000_000),
+    ] {
+        stake(&mut app, &pool, staker, *amount);
+    }
+
+    setup_with(app, pool)
+}
+
+/// Creates a factory whose voting power comes from `pool`, which can be any contract with the
+/// staking pool's interface
+fn setup_with(mut app: App, pool: HumanAddr) -> Setup {
+    let factory_code = app.store_code(poll_factory_contract());
+    let poll_code = app.store_code(secret_poll_contract());
+
+    let (factory, _) = app
+        .instantiate(
+            factory_code,
+            "admin",
+            &FactoryInitMsg {
+                prng_seed: Binary::from(b"seed".to_vec()),
+                poll_contract: PollContract {
+                    code_id: poll_code,
+                    code_hash: app.code_hash(poll_code).unwrap(),
+                },
+                staking_pool: SecretContract {
+                    address: pool.clone(),
+                    contract_hash: app.contract_code_hash(&pool).unwrap(),
+                },
+                default_poll_config: PollConfig {
+                    duration: 1000,
+                    quorum: 3300,
+                    min_threshold: 5000,
+                    quorum_comparison: Comparison::Inclusive,
+                    threshold_comparison: Comparison::Exclusive,
+                    veto_threshold: 3340,
+                    revote_policy: RevotePolicy::Allowed,
+                    voting_power_mode: VotingPowerMode::Balance,
+                    power_cap: VotingPowerCap::Uncapped,
+                    lock_stake: false,
+                },
+                poll_config_bounds: PollConfigBounds {
+                    min_duration: 100,
+                    max_duration: 100_000,
+                    min_quorum: 1000,
+                    max_quorum: 5000,
+                    min_threshold: 0,
+                    max_threshold: 10_000,
+                    min_veto_threshold: 1000,
+                    max_veto_threshold: 5000,
+                    max_start_delay: 604_800,
+                    max_power_cap: VotingPowerCap::Uncapped,
+                },
+                min_staked: Uint128(1_000_000),
+                reveal_com: RevealCommittee {
+                    n: 1,
+                    revealers: vec![HumanAddr("rev1".to_string())],
+                },
+                response_block_size: None,
+                council: Some(HumanAddr("council".to_string())),
+            },
+            "factory",
+        )
+        .unwrap();
+
+    app.execute(
+        &pool,
+        "admin",
+        &LPStakingHandleMsg::AddSubs {
+            contracts: vec![SecretContract {
+                address: factory.clone(),
+                contract_hash: app.code_hash(factory_code).unwrap(),
+            }],
+        },
+    )
+    .unwrap();
+
+    Setup { app, factory, pool }
+}
+
+fn stake(app: &mut App, pool: &HumanAddr, staker: &str, amount: u128) {
+    app.execute(
+        pool,
+        staker,
+        &LPStakingHandleMsg::SetViewingKey {
+            key: "key".to_string(),
+            padding: None,
+        },
+    )
+    .unwrap();
+    app.execute(
+        pool,
+        "sefi token",
+        &LPStakingHandleMsg::Receive {
+            sender: HumanAddr(staker.to_string()),
+            from: HumanAddr(staker.to_string()),
+            amount: Uint128(amount),
+            msg: to_binary(&LPStakingReceiveMsg::Deposit {}).unwrap(),
+        },
+    )
+    .unwrap();
+}
+
+fn new_poll_msg() -> PollFactoryHandleMsg {
+    new_poll_msg_with(None)
+}
+
+fn private_poll_msg() -> PollFactoryHandleMsg {
+    new_poll_msg_with(Some(true))
+}
+
+fn new_poll_msg_with(private_participation: Option<bool>) -> PollFactoryHandleMsg {
+    PollFactoryHandleMsg::NewPoll {
+        poll_metadata: PollMetadata {
+            title: "Add a new pool".to_string(),
+            description: "Should we add a new pool?".to_string(),
+            vote_type: "SEFI Community Spending".to_string(),
+            author_addr: None,
+            author_alias: "alice".to_string(),
+        },
+        poll_config: None,
+        poll_choices: vec!["Yes".to_string(), "No".to_string()],
+        pool_viewing_key: "key".to_string(),
+        start_time: None,
+        abstain_choice: None,
+        veto_choice: None,
+        private_participation,
+        padding: None,
+    }
+}
+
+fn create_poll(setup: &mut Setup, author: &str) -> HumanAddr {
+    create_poll_with(setup, author, new_poll_msg())
+}
+
+fn create_poll_with(setup: &mut Setup, author: &str, msg: PollFactoryHandleMsg) -> HumanAddr {
+    let response = setup.app.execute(&setup.factory, author, &msg).unwrap();
+    assert_eq!(response.instantiated.len(), 1);
+
+    response.instantiated[0].clone()
+}
+
+fn vote_with_key(
+    setup: &mut Setup,
+    poll: &HumanAddr,
+    voter: &str,
+    choice: u8,
+    key: &str,
+) -> StdResult<AppResponse> {
+    setup.app.execute(
+        poll,
+        voter,
+        &PollHandleMsg::Vote {
+            choice,
+            staking_pool_viewing_key: key.to_string(),
+            salt: format!("salt-{}", voter),
+            padding: None,
+        },
+    )
+}
+
+fn vote(setup: &mut Setup, poll: &HumanAddr, voter: &str, choice: u8) {
+    vote_with_key(setup, poll, voter, choice, "key").unwrap();
+}
+
+fn query_vote(setup: &Setup, poll: &HumanAddr, voter: &str, key: &str) -> StdResult<(u8, u128)> {
+    match setup.app.query(
+        poll,
+        &PollQuery::Vote {
+            voter: HumanAddr(voter.to_string()),
+            key: key.to_string(),
+        },
+    )? {
+        PollAnswer::Vote {
+            choice,
+            voting_power,
+            ..
+        } => Ok((choice, voting_power.u128())),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+fn query_has_voted(
+    setup: &Setup,
+    poll: &HumanAddr,
+    voter: &str,
+    key: Option<&str>,
+) -> StdResult<bool> {
+    match setup.app.query(
+        poll,
+        &PollQuery::HasVoted {
+            voter: HumanAddr(voter.to_string()),
+            key: key.map(String::from),
+        },
+    )? {
+        PollAnswer::HasVoted { has_voted } => Ok(has_voted),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+fn finalize(setup: &mut Setup, poll: &HumanAddr) {
+    let hash = match setup.app.query(poll, &PollQuery::RollingHash {}).unwrap() {
+        PollAnswer::RollingHash { hash } => hash,
+        _ => panic!("unexpected answer"),
+    };
+    setup
+        .app
+        .execute(
+            poll,
+            "rev1",
+            &PollHandleMsg::Finalize {
+                rolling_hash: hash,
+                padding: None,
+            },
+        )
+        .unwrap();
+}
+
+#[test]
+fn test_new_poll_registers_with_factory() {
+    let mut setup = setup();
+    let poll = create_poll(&mut setup, "alice");
+
+    let now = setup.app.block().time;
+    let active_polls = match setup
+        .app
+        .query(
+            &setup.factory,
+            &FactoryQuery::ActivePolls { current_time: now },
+        )
+        .unwrap()
+    {
+        FactoryAnswer::ActivePolls { active_polls } => active_polls,
+        _ => panic!("unexpected answer"),
+    };
+    assert_eq!(active_polls.len(), 1);
+    assert_eq!(active_polls[0].address, poll);
+    assert_eq!(active_polls[0].end_time, now + 1000);
+
+    match setup
+        .app
+        .query(&setup.factory, &FactoryQuery::Poll { address: poll })
+        .unwrap()
+    {
+        FactoryAnswer::Poll { poll } => assert!(poll.outcome.is_none()),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+#[test]
+fn test_new_poll_below_minimum_stake() {
+    let mut setup = setup();
+    stake(&mut setup.app, &setup.pool, "dave", 999_999);
+
+    let err = setup
+        .app
+        .execute(&setup.factory, "dave", &new_poll_msg())
+        .unwrap_err();
+    match err {
+        StdError::GenericErr { msg, .. } => assert_eq!(
+            ErrorCode::from_message(&msg),
+            Some(ErrorCode::InsufficientStake)
+        ),
+        _ => panic!("unexpected error: {:?}", err),
+    }
+}
+
+#[test]
+fn test_zero_deposit() {
+    let mut setup = setup();
+    let err = setup
+        .app
+        .execute(
+            &setup.pool,
+            "sefi token",
+            &LPStakingHandleMsg::Receive {
+                sender: HumanAddr("dave".to_string()),
+                from: HumanAddr("dave".to_string()),
+                amount: Uint128(0),
+                msg: to_binary(&LPStakingReceiveMsg::Deposit {}).unwrap(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(
+        err,
+        StdError::generic_err("deposit amount must be positive")
+    );
+}
+
+#[test]
+fn test_failed_sub_message_reverts_call() {
+    let mut setup = setup();
+    setup
+        .app
+        .execute(
+            &setup.pool,
+            "admin",
+            &LPStakingHandleMsg::AddSubs {
+                contracts: vec![SecretContract {
+                    address: HumanAddr("not a contract".to_string()),
+                    contract_hash: "".to_string(),
+                }],
+            },
+        )
+        .unwrap();
+
+    // The pool stores the deposit before notifying its subscribers, one of which doesn't exist
+    let err = setup
+        .app
+        .execute(
+            &setup.pool,
+            "sefi token",
+            &LPStakingHandleMsg::Receive {
+                sender: HumanAddr("alice".to_string()),
+                from: HumanAddr("alice".to_string()),
+                amount: Uint128(1_000_000),
+                msg: to_binary(&LPStakingReceiveMsg::Deposit {}).unwrap(),
+            },
+        )
+        .unwrap_err();
+    assert_eq!(err, StdError::generic_err("no contract at not a contract"));
+
+    match setup
+        .app
+        .query(&setup.pool, &LPStakingQueryMsg::TotalLocked { as_of: None })
+        .unwrap()
+    {
+        LPStakingQueryAnswer::TotalLocked { amount } => assert_eq!(amount, Uint128(10_000_000)),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+#[test]
+fn test_poll_lifecycle() {
+    let mut setup = setup();
+    let poll = create_poll(&mut setup, "alice");
+
+    vote(&mut setup, &poll, "alice", 0);
+    vote(&mut setup, &poll, "bob", 1);
+
+    setup.app.advance_time(1000);
+    finalize(&mut setup, &poll);
+
+    match setup.app.query(&poll, &PollQuery::Result {}).unwrap() {
+        PollAnswer::Result { result, .. } => {
+            assert_eq!(result.outcome, Outcome::Passed);
+            assert_eq!(result.winning_choice, Some(0));
+            assert_eq!(result.turnout, 9000);
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    // The poll reported back to the factory and is no longer active
+    match setup
+        .app
+        .query(&setup.factory, &FactoryQuery::Poll { address: poll })
+        .unwrap()
+    {
+        FactoryAnswer::Poll { poll } => {
+            assert_eq!(poll.outcome, Some(Outcome::Passed));
+            assert_eq!(poll.winning_choice, Some(0));
+        }
+        _ => panic!("unexpected answer"),
+    }
+    let now = setup.app.block().time;
+    match setup
+        .app
+        .query(
+            &setup.factory,
+            &FactoryQuery::ActivePolls { current_time: now },
+        )
+        .unwrap()
+    {
+        FactoryAnswer::ActivePolls { active_polls } => assert!(active_polls.is_empty()),
+        _ => panic!("unexpected answer"),
+    }
+}
+
+#[test]
+fn test_viewing_key_required() {
+    let mut setup = setup();
+    let poll = create_poll(&mut setup, "alice");
+
+    assert!(vote_with_key(&mut setup, &poll, "bob", 0, "wrong key").is_err());
+    // Never set a viewing key
+    assert!(vote_with_key(&mut setup, &poll, "eve", 0, "key").is_err());
+
+    vote(&mut setup, &poll, "bob", 0);
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (0, 3_000_000)
+    );
+    assert!(query_vote(&setup, &poll, "bob", "wrong key").is_err());
+}
+
+#[test]
+fn test_stake_changes_update_votes() {
+    let mut setup = setup();
+    let poll = create_poll(&mut setup, "alice");
+
+    vote(&mut setup, &poll, "alice", 0);
+    vote(&mut setup, &poll, "bob", 1);
+
+    // The pool notifies the factory, which forwards the update to every active poll
+    let response = setup
+        .app
+        .execute(
+            &setup.pool,
+            "alice",
+            &LPStakingHandleMsg::Redeem {
+                amount: Some(Uint128(5_000_000)),
+            },
+        )
+        .unwrap();
+    assert!(response.logs.iter().any(|l| l.contract == poll));
+    assert_eq!(
+        query_vote(&setup, &poll, "alice", "key").unwrap(),
+        (0, 1_000_000)
+    );
+
+    stake(&mut setup.app, &setup.pool, "bob", 1_000_000);
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (1, 4_000_000)
+    );
+
+    // Total locked is now 6M, of which 5M voted
+    setup.app.advance_time(1000);
+    finalize(&mut setup, &poll);
+    match setup.app.query(&poll, &PollQuery::Result {}).unwrap() {
+        PollAnswer::Result { result, .. } => {
+            assert_eq!(result.outcome, Outcome::Passed);
+            assert_eq!(result.winning_choice, Some(1));
+            assert_eq!(result.turnout, 8333);
+        }
+        _ => panic!("unexpected answer"),
+    }
+}
+
+#[test]
+fn test_private_participation() {
+    let mut setup = setup();
+    let public_poll = create_poll(&mut setup, "alice");
+    let private_poll = create_poll_with(&mut setup, "alice", private_poll_msg());
+
+    vote(&mut setup, &public_poll, "bob", 0);
+    let response = vote_with_key(&mut setup, &private_poll, "bob", 0, "key").unwrap();
+    for attribute in response.logs_of(&private_poll) {
+        assert!(!attribute.value.contains("bob"));
+    }
+
+    assert!(query_has_voted(&setup, &public_poll, "bob", None).unwrap());
+    assert!(query_has_voted(&setup, &private_poll, "bob", None).is_err());
+    assert!(query_has_voted(&setup, &private_poll, "bob", Some("wrong key")).is_err());
+    assert!(query_has_voted(&setup, &private_poll, "bob", Some("key")).unwrap());
+    assert!(!query_has_voted(&setup, &private_poll, "carol", Some("key")).unwrap());
+}
+
+#[test]
+fn test_stake_age_voting_power() {
+    let mut setup = setup();
+    setup.app.advance_time(1000);
+    stake(&mut setup.app, &setup.pool, "dave", 3_000_000);
+
+    let mut msg = new_poll_msg();
+    if let PollFactoryHandleMsg::NewPoll { poll_config, .. } = &mut msg {
+        *poll_config = Some(PollConfig {
+            duration: 1000,
+            quorum: 3300,
+            min_threshold: 5000,
+            quorum_comparison: Comparison::Inclusive,
+            threshold_comparison: Comparison::Exclusive,
+            veto_threshold: 3340,
+            revote_policy: RevotePolicy::Allowed,
+            voting_power_mode: VotingPowerMode::StakeAge {
+                max_multiplier: 20_000,
+                boost_period: 1000,
+            },
+            power_cap: VotingPowerCap::Uncapped,
+            lock_stake: false,
+        });
+    }
+    let poll = create_poll_with(&mut setup, "alice", msg);
+
+    // Bob staked before the boost period, while Dave staked right before the poll
+    vote(&mut setup, &poll, "bob", 0);
+    vote(&mut setup, &poll, "dave", 1);
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (0, 6_000_000)
+    );
+    assert_eq!(
+        query_vote(&setup, &poll, "dave", "key").unwrap(),
+        (1, 3_000_000)
+    );
+
+    // Doubling the stake halves its age
+    stake(&mut setup.app, &setup.pool, "bob", 3_000_000);
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (0, 9_000_000)
+    );
+}
+
+#[test]
+fn test_voting_locks_stake() {
+    let mut setup = setup();
+    let locking_poll_msg = || {
+        let mut msg = new_poll_msg();
+        if let PollFactoryHandleMsg::NewPoll { poll_config, .. } = &mut msg {
+            *poll_config = Some(PollConfig {
+                duration: 1000,
+                quorum: 3300,
+                min_threshold: 5000,
+                quorum_comparison: Comparison::Inclusive,
+                threshold_comparison: Comparison::Exclusive,
+                veto_threshold: 3340,
+                revote_policy: RevotePolicy::Allowed,
+                voting_power_mode: VotingPowerMode::Balance,
+                power_cap: VotingPowerCap::Uncapped,
+                lock_stake: true,
+            });
+        }
+        msg
+    };
+    let poll = create_poll_with(&mut setup, "alice", locking_poll_msg());
+    let other_poll = create_poll(&mut setup, "alice");
+
+    let redeem = |setup: &mut Setup, staker: &str| {
+        let pool = setup.pool.clone();
+        setup.app.execute(
+            &pool,
+            staker,
+            &LPStakingHandleMsg::Redeem {
+                amount: Some(Uint128(1_000_000)),
+            },
+        )
+    };
+
+    // Bob can't vote, redeem, and vote again with the same stake from another address
+    vote(&mut setup, &poll, "bob", 0);
+    vote(&mut setup, &other_poll, "carol", 0); // Doesn't lock
+    assert!(redeem(&mut setup, "bob").is_err());
+    redeem(&mut setup, "carol").unwrap();
+
+    // Cancelling the poll unlocks its voters' stake
+    setup
+        .app
+        .execute(&poll, "alice", &PollHandleMsg::Cancel { padding: None })
+        .unwrap();
+    redeem(&mut setup, "bob").unwrap();
+
+    // Otherwise, it's locked until the poll ends
+    let poll = create_poll_with(&mut setup, "alice", locking_poll_msg());
+    vote(&mut setup, &poll, "bob", 0);
+    assert!(redeem(&mut setup, "bob").is_err());
+    setup.app.advance_time(1001);
+    redeem(&mut setup, "bob").unwrap();
+}
+
+#[test]
+fn test_vote_escrow_voting_power() {
+    let mut app = App::new();
+    let token_code = app.store_code(mock_token_contract());
+    let escrow_code = app.store_code(vote_escrow_contract());
+
+    let (token, _) = app
+        .instantiate(
+            token_code,
+            "admin",
+            &mock_token::InitMsg {
+                initial_balances: vec![
+                    mock_token::InitialBalance {
+                        address: HumanAddr("alice".to_string()),
+                        amount: Uint128(6_000_000),
+                    },
+                    mock_token::InitialBalance {
+                        address: HumanAddr("bob".to_string()),
+                        amount: Uint128(3_000_000),
+                    },
+                ],
+            },
+            "token",
+        )
+        .unwrap();
+    let (escrow, _) = app
+        .instantiate(
+            escrow_code,
+            "admin",
+            &EscrowInitMsg {
+                token: SecretContract {
+                    address: token.clone(),
+                    contract_hash: app.code_hash(token_code).unwrap(),
+                },
+                max_lock_duration: 4 * WEEK,
+            },
+            "escrow",
+        )
+        .unwrap();
+
+    // Unlock times are rounded down to a whole week, so start on one
+    let now = app.block().time;
+    app.advance_time(WEEK - now % WEEK);
+    let start = app.block().time;
+    for (staker, amount, weeks) in &[("alice", 6_000_000, 4), ("bob", 3_000_000, 2)] {
+        app.execute(
+            &escrow,
+            staker,
+            &EscrowHandleMsg::SetViewingKey {
+                key: "key".to_string(),
+                padding: None,
+            },
+        )
+        .unwrap();
+        app.execute(
+            &token,
+            staker,
+            &mock_token::HandleMsg::Send {
+                recipient: escrow.clone(),
+                amount: Uint128(*amount),
+                msg: Some(
+                    to_binary(&EscrowReceiveMsg::CreateLock {
+                        unlock_time: start + weeks * WEEK,
+                    })
+                    .unwrap(),
+                ),
+            },
+        )
+        .unwrap();
+    }
+
+    let mut setup = setup_with(app, escrow);
+    let poll = create_poll(&mut setup, "alice");
+    vote(&mut setup, &poll, "alice", 0);
+    vote(&mut setup, &poll, "bob", 1);
+    assert_eq!(
+        query_vote(&setup, &poll, "alice", "key").unwrap(),
+        (0, 6_000_000)
+    );
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (1, 1_500_000)
+    );
+
+    // Decay only reaches the poll once someone checkpoints it
+    setup.app.advance_time(500);
+    setup
+        .app
+        .execute(
+            &setup.pool,
+            "carol",
+            &EscrowHandleMsg::Checkpoint {
+                stakers: vec![HumanAddr("alice".to_string()), HumanAddr("bob".to_string())],
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        query_vote(&setup, &poll, "alice", "key").unwrap(),
+        (0, 5_998_759)
+    );
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (1, 1_499_379)
+    );
+
+    // Extending a lock updates the vote right away
+    setup
+        .app
+        .execute(
+            &setup.pool,
+            "bob",
+            &EscrowHandleMsg::ExtendLock {
+                unlock_time: start + 4 * WEEK,
+                padding: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        query_vote(&setup, &poll, "bob", "key").unwrap(),
+        (1, 2_999_379)
+    );
+
+    setup.app.advance_time(500);
+    finalize(&mut setup, &poll);
+    match setup.app.query(&poll, &PollQuery::Result {}).unwrap() {
+        PollAnswer::Result { result, .. } => {
+            assert_eq!(result.outcome, Outcome::Passed);
+            assert_eq!(result.winning_choice, Some(0));
+        }
+        _ => panic!("unexpected answer"),
+    }
+
+    // Once the lock expires, the tokens can be withdrawn
+    let escrow = setup.pool.clone();
+    assert!(setup
+        .app
+        .execute(&escrow, "bob", &EscrowHandleMsg::Withdraw { padding: None })
+        .is_err());
+    setup.app.advance_time(4 * WEEK);
+    setup
+        .app
+        .execute(&escrow, "bob", &EscrowHandleMsg::Withdraw { padding: None })
+        .unwrap();
+    match setup
+        .app
+        .query(
+            &token,
+            &mock_token::QueryMsg::Balance {
+                address: HumanAddr("bob".to_string()),
+            },
+        )
+        .unwrap()
+    {
+        mock_token::QueryAnswer::Balance { amount } => assert_eq!(amount, Uint128(3_000_000)),
+    }
+}
@@ -0,0 +1,322 @@
+//! The errors returned by the voting contracts.
+//!
+//! Contracts can only return `StdError`s, so a `ContractError` is converted into a generic error
+//! whose message starts with its code, e.g. `[2002] vote has ended`. Codes are stable: a variant
+//! keeps its code even if its message changes, and codes of removed variants are never reused.
+//! Clients should match on `ErrorCode::from_message` rather than on message contents.
+use cosmwasm_std::{HumanAddr, StdError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use snafu::Snafu;
+
+#[derive(Snafu, Clone, Debug, PartialEq)]
+#[snafu(visibility = "pub")]
+pub enum ContractError {
+    // Poll configuration
+    #[snafu(display(
+        "invalid duration bounds: minimum must be positive and at most the maximum (got {}-{})",
+        min,
+        max
+    ))]
+    InvalidDurationBounds { min: u64, max: u64 },
+    #[snafu(display(
+        "invalid {} bounds: expected 0 <= min <= max <= 10000 (got {}-{})",
+        name,
+        min,
+        max
+    ))]
+    InvalidPercentageBounds { name: String, min: u16, max: u16 },
+    #[snafu(display(
+        "poll duration must be between {} and {} seconds, got {}",
+        min,
+        max,
+        duration
+    ))]
+    DurationOutOfBounds { min: u64, max: u64, duration: u64 },
+    #[snafu(display(
+        "{} must be between {} and {} basis points, got {}",
+        name,
+        min,
+        max,
+        value
+    ))]
+    PercentageOutOfBounds {
+        name: String,
+        min: u16,
+        max: u16,
+        value: u16,
+    },
+    #[snafu(display("response block size must be positive"))]
+    InvalidResponseBlockSize,
+    #[snafu(display("you have to provide at least two choices"))]
+    NotEnoughChoices,
+    #[snafu(display("{} choice {} does not exist in this poll", name, choice))]
+    InvalidSpecialChoice { name: String, choice: u8 },
+    #[snafu(display("the same choice can't be both abstain and veto"))]
+    AbstainIsVeto,
+    #[snafu(display("poll title must be at least 2 characters long"))]
+    TitleTooShort,
+    #[snafu(display("poll description must be at least 10 characters long"))]
+    DescriptionTooShort,
+    #[snafu(display("poll author alias must be at least 3 characters long"))]
+    AuthorAliasTooShort,
+    #[snafu(display("poll start time cannot be in the past"))]
+    StartTimeInPast,
+    #[snafu(display(
+        "re-vote lock period must be shorter than the poll duration ({} seconds), got {}",
+        duration,
+        lock_period
+    ))]
+    LockPeriodTooLong { lock_period: u64, duration: u64 },
+    #[snafu(display(
+        "stake age boost must have a multiplier of 10000-50000 basis points and a positive period (got {}, {} seconds)",
+        max_multiplier,
+        boost_period
+    ))]
+    InvalidStakeAgeBoost {
+        max_multiplier: u16,
+        boost_period: u64,
+    },
+    #[snafu(display(
+        "voting power cap must be a positive amount or 1-10000 basis points of the total staked"
+    ))]
+    InvalidVotingPowerCap,
+    #[snafu(display(
+        "member list must not be empty, and every member must appear once with a positive weight"
+    ))]
+    InvalidMemberList,
+    #[snafu(display("stake age voting power requires a staking pool"))]
+    StakeAgeWithoutStakingPool,
+    #[snafu(display("locking stake requires a staking pool and a factory to lock it through"))]
+    LockStakeUnsupported,
+    #[snafu(display("poll can start at most {} seconds from now, got {}", max_delay, delay))]
+    StartTimeTooFar { max_delay: u64, delay: u64 },
+    #[snafu(display(
+        "voting power cap must be of the same kind as the admin-set maximum and no looser"
+    ))]
+    VotingPowerCapTooLoose,
+
+    // Poll lifecycle
+    #[snafu(display("vote has not started yet"))]
+    VoteNotStarted,
+    #[snafu(display("vote has ended"))]
+    VoteEnded,
+    #[snafu(display("vote has not ended yet"))]
+    VoteNotEnded,
+    #[snafu(display("choice {} does not exist in this poll", choice))]
+    InvalidChoice { choice: u8 },
+    #[snafu(display("poll was cancelled"))]
+    PollCancelled,
+    #[snafu(display("incorrect rolling hash"))]
+    IncorrectRollingHash,
+    #[snafu(display("already finalized the vote"))]
+    AlreadyRevealed,
+    #[snafu(display("vote has already been finalized"))]
+    AlreadyFinalized,
+    #[snafu(display("vote can only be cancelled before it ends"))]
+    CancelAfterEnd,
+    #[snafu(display("vote hasn't been finalized yet"))]
+    NotFinalized,
+    #[snafu(display("vote hasn't passed quorum"))]
+    QuorumNotPassed,
+    #[snafu(display("participation in this poll is private, a viewing key is required"))]
+    ViewingKeyRequired,
+    #[snafu(display("something is wrong with the lp staking contract.."))]
+    UnexpectedStakingPoolAnswer,
+    #[snafu(display("this poll doesn't allow changing your vote"))]
+    RevoteNotAllowed,
+    #[snafu(display("you can change your vote at most {} times", max_revotes))]
+    RevoteLimitReached { max_revotes: u32 },
+    #[snafu(display("ballots are locked, the poll is about to end"))]
+    BallotsLocked,
+    #[snafu(display("only members can vote in this poll"))]
+    NotAMember,
+
+    // Tally and storage
+    #[snafu(display("tally overflow"))]
+    TallyOverflow,
+    #[snafu(display("tally underflow"))]
+    TallyUnderflow,
+    #[snafu(display("storage is corrupted"))]
+    StorageCorrupted,
+    #[snafu(display(
+        "poll storage is at version {}, but this code expects version {}",
+        found,
+        expected
+    ))]
+    StorageVersionMismatch { found: u32, expected: u32 },
+    #[snafu(display("voting power overflow"))]
+    VotingPowerOverflow,
+
+    // Poll factory
+    #[snafu(display(
+        "insufficient staked amount. Minimum staked SEFI to create a poll is {}",
+        min_staked
+    ))]
+    InsufficientStake { min_staked: u128 },
+    #[snafu(display("challenge did not match. This function can be called only as a callback from a new poll contract"))]
+    ChallengeMismatch,
+    #[snafu(display("poll result was already reported"))]
+    ResultAlreadyReported,
+    #[snafu(display("{} is not a poll created by this factory", address))]
+    UnknownPoll { address: HumanAddr },
+
+    // Vote escrow
+    #[snafu(display("no tokens are locked"))]
+    NoLock,
+    #[snafu(display("tokens are already locked. Increase the amount or extend the lock instead"))]
+    LockExists,
+    #[snafu(display("lock has expired, withdraw it first"))]
+    LockExpired,
+    #[snafu(display("tokens are locked until {}", unlock_time))]
+    LockNotExpired { unlock_time: u64 },
+    #[snafu(display(
+        "unlock time must be after {} and at most {}, got {} (rounded down to a whole week)",
+        min,
+        max,
+        unlock_time
+    ))]
+    InvalidUnlockTime {
+        min: u64,
+        max: u64,
+        unlock_time: u64,
+    },
+    #[snafu(display("amount to lock must be positive"))]
+    NothingToLock,
+    #[snafu(display("maximum lock duration must be at least a week"))]
+    InvalidMaxLockDuration,
+    #[snafu(display("locked amount or voting power overflow"))]
+    LockOverflow,
+}
+
+macro_rules! error_codes {
+    ($($variant:ident = $code:literal,)*) => {
+        /// A `ContractError` without its details
+        #[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+        #[serde(rename_all = "snake_case")]
+        pub enum ErrorCode {
+            $($variant = $code,)*
+        }
+
+        impl ErrorCode {
+            pub fn from_u16(code: u16) -> Option<Self> {
+                match code {
+                    $($code => Some(ErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+
+        impl ContractError {
+            pub fn code(&self) -> ErrorCode {
+                match self {
+                    $(ContractError::$variant { .. } => ErrorCode::$variant,)*
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    InvalidDurationBounds = 1001,
+    InvalidPercentageBounds = 1002,
+    DurationOutOfBounds = 1003,
+    PercentageOutOfBounds = 1004,
+    InvalidResponseBlockSize = 1005,
+    NotEnoughChoices = 1006,
+    InvalidSpecialChoice = 1007,
+    AbstainIsVeto = 1008,
+    TitleTooShort = 1009,
+    DescriptionTooShort = 1010,
+    AuthorAliasTooShort = 1011,
+    StartTimeInPast = 1012,
+    LockPeriodTooLong = 1013,
+    InvalidStakeAgeBoost = 1014,
+    InvalidVotingPowerCap = 1015,
+    InvalidMemberList = 1017,
+    StakeAgeWithoutStakingPool = 1018,
+    LockStakeUnsupported = 1019,
+    StartTimeTooFar = 1020,
+    VotingPowerCapTooLoose = 1021,
+
+    VoteNotStarted = 2001,
+    VoteEnded = 2002,
+    VoteNotEnded = 2003,
+    InvalidChoice = 2004,
+    PollCancelled = 2005,
+    IncorrectRollingHash = 2006,
+    AlreadyRevealed = 2007,
+    AlreadyFinalized = 2008,
+    CancelAfterEnd = 2009,
+    NotFinalized = 2010,
+    QuorumNotPassed = 2011,
+    ViewingKeyRequired = 2012,
+    UnexpectedStakingPoolAnswer = 2013,
+    RevoteNotAllowed = 2014,
+    RevoteLimitReached = 2015,
+    BallotsLocked = 2016,
+    NotAMember = 2017,
+
+    TallyOverflow = 3001,
+    TallyUnderflow = 3002,
+    StorageCorrupted = 3003,
+    StorageVersionMismatch = 3004,
+    VotingPowerOverflow = 3008,
+
+    InsufficientStake = 4001,
+    ChallengeMismatch = 4002,
+    ResultAlreadyReported = 4003,
+    UnknownPoll = 4004,
+
+    NoLock = 5001,
+    LockExists = 5002,
+    LockExpired = 5003,
+    LockNotExpired = 5004,
+    InvalidUnlockTime = 5005,
+    NothingToLock = 5006,
+    InvalidMaxLockDuration = 5007,
+    LockOverflow = 5008,
+}
+
+impl ErrorCode {
+    /// Finds the code in an error message returned by a contract, which may be wrapped by the node
+    pub fn from_message(msg: &str) -> Option<Self> {
+        msg.match_indices('[').find_map(|(start, _)| {
+            let rest = &msg[start + 1..];
+            let end = rest.find(']')?;
+            rest[..end].parse().ok().and_then(Self::from_u16)
+        })
+    }
+}
+
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        StdError::generic_err(format!("[{}] {}", err.code() as u16, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn error_code_round_trip() {
+        let err = StdError::from(ContractError::InvalidChoice { choice: 3 });
+        assert_eq!(
+            err,
+            StdError::generic_err("[2004] choice 3 does not exist in this poll")
+        );
+
+        let msg = match err {
+            StdError::GenericErr { msg, .. } => msg,
+            _ => unreachable!(),
+        };
+        let wrapped = format!("encrypted: {}: generic error: {}", "[2]", msg);
+        assert_eq!(
+            ErrorCode::from_message(&wrapped),
+            Some(ErrorCode::InvalidChoice)
+        );
+        assert_eq!(ErrorCode::from_message("vote has ended"), None);
+        assert_eq!(ErrorCode::from_message("[9999] unknown"), None);
+    }
+}
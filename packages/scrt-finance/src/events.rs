@@ -0,0 +1,70 @@
+//! The log attributes emitted by the voting and vote escrow contracts, for indexers.
+//!
+//! Every handler logs an `action`. Handlers concerning a single poll also log the poll's address
+//! as `poll_id`, and those that move a poll to another phase log the new `phase`. Results are
+//! logged as `outcome`, plus `winning_choice` when there is one.
+//!
+//! Voter addresses, choices, voting power and locked amounts are never logged, and a handler logs
+//! the same attributes whether or not the staker involved has voted.
+use crate::secret_vote_types::Outcome;
+use cosmwasm_std::{log, LogAttribute};
+
+pub const ACTION: &str = "action";
+pub const POLL_ID: &str = "poll_id";
+pub const PHASE: &str = "phase";
+pub const OUTCOME: &str = "outcome";
+pub const WINNING_CHOICE: &str = "winning_choice";
+pub const NUM_REVEALED: &str = "num_revealed";
+
+// Poll factory actions
+pub const NEW_POLL: &str = "new_poll";
+pub const REGISTER_POLL: &str = "register_poll";
+pub const REPORT_RESULT: &str = "report_result";
+pub const LOCK_STAKE: &str = "lock_stake";
+pub const UPDATE_DEFAULT_POLL_CONFIG: &str = "update_default_poll_config";
+pub const UPDATE_POLL_CONFIG_BOUNDS: &str = "update_poll_config_bounds";
+pub const UPDATE_CONFIG: &str = "update_config";
+pub const CHANGE_ADMIN: &str = "change_admin";
+
+// Secret poll actions
+pub const INIT: &str = "init";
+pub const VOTE: &str = "vote";
+pub const FINALIZE: &str = "finalize";
+pub const CANCEL: &str = "cancel";
+
+// Vote escrow actions
+pub const CREATE_LOCK: &str = "create_lock";
+pub const INCREASE_LOCK_AMOUNT: &str = "increase_lock_amount";
+pub const EXTEND_LOCK: &str = "extend_lock";
+pub const WITHDRAW: &str = "withdraw";
+pub const CHECKPOINT: &str = "checkpoint";
+pub const UPDATE_SUBSCRIBERS: &str = "update_subscribers";
+pub const UNLOCK_STAKE: &str = "unlock_stake";
+
+// Both
+pub const UPDATE_VOTING_POWER: &str = "update_voting_power";
+
+pub fn action(action: &str) -> LogAttribute {
+    log(ACTION, action)
+}
+
+/// The outcome of a poll, and its winning choice if it has one
+pub fn result(outcome: Outcome, winning_choice: Option<u8>) -> Vec<LogAttribute> {
+    let mut logs = vec![log(OUTCOME, outcome_name(outcome))];
+    if let Some(choice) = winning_choice {
+        logs.push(log(WINNING_CHOICE, choice));
+    }
+
+    logs
+}
+
+pub fn outcome_name(outcome: Outcome) -> &'static str {
+    match outcome {
+        Outcome::Passed => "passed",
+        Outcome::Rejected => "rejected",
+        Outcome::QuorumNotMet => "quorum_not_met",
+        Outcome::Tie => "tie",
+        Outcome::Vetoed => "vetoed",
+        Outcome::Cancelled => "cancelled",
+    }
+}
@@ -1,9 +1,6 @@
+pub mod errors;
+pub mod events;
+pub mod lp_staking_msg;
 pub mod secret_vote_types;
-
-#[cfg(test)]
-mod tests {
-    #[test]
-    fn it_works() {
-        assert_eq!(2 + 2, 4);
-    }
-}
+pub mod types;
+pub mod voting_power;
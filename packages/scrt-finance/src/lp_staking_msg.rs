@@ -0,0 +1,118 @@
+//! The subset of the SEFI staking pool's interface that the voting contracts rely on
+use crate::types::SecretContract;
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LPStakingHandleMsg {
+    Redeem {
+        amount: Option<Uint128>, // Redeems everything if not set
+    },
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Binary, // LPStakingReceiveMsg
+    },
+    CreateViewingKey {
+        entropy: String,
+        padding: Option<String>,
+    },
+    SetViewingKey {
+        key: String,
+        padding: Option<String>,
+    },
+
+    // Subscribers. A locked stake can't be redeemed until `until`, or until its poll is unlocked
+    LockStake {
+        staker: HumanAddr,
+        poll: HumanAddr,
+        until: u64,
+    },
+    UnlockStake {
+        poll: HumanAddr,
+    },
+
+    // Admin
+    AddSubs {
+        contracts: Vec<SecretContract>,
+    },
+    RemoveSubs {
+        contracts: Vec<HumanAddr>,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LPStakingReceiveMsg {
+    Deposit {},
+}
+
+/// Sent by the staking pool to each of its subscribers whenever a staker's balance changes.
+/// Serializes the same as `PollFactoryHandleMsg::UpdateVotingPower`. `staked_since` is the
+/// balance-weighted average deposit time, for pools that track it
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LPStakingHookMsg {
+    UpdateVotingPower {
+        voter: HumanAddr,
+        new_power: Uint128,
+        staked_since: Option<u64>,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LPStakingQueryMsg {
+    // `as_of` is the querying contract's block time, as queries don't get one. Only pools whose
+    // balances change with time (i.e. the vote escrow) use it, others ignore it
+    TotalLocked {
+        as_of: Option<u64>,
+    },
+
+    // Authenticated
+    Balance {
+        address: HumanAddr,
+        key: String,
+        as_of: Option<u64>,
+    },
+    StakeInfo {
+        address: HumanAddr,
+        key: String,
+    },
+}
+
+#[derive(Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LPStakingQueryAnswer {
+    TotalLocked { amount: Uint128 },
+    Balance { amount: Uint128 },
+    StakeInfo { amount: Uint128, staked_since: u64 },
+    ViewingKeyError { msg: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret_vote_types::PollFactoryHandleMsg;
+    use cosmwasm_std::to_binary;
+
+    #[test]
+    fn voting_power_hook_matches_factory_msg() {
+        let voter = HumanAddr("voter".to_string());
+        let hook = LPStakingHookMsg::UpdateVotingPower {
+            voter: voter.clone(),
+            new_power: Uint128(42),
+            staked_since: Some(1_600_000_000),
+        };
+        let factory_msg = PollFactoryHandleMsg::UpdateVotingPower {
+            voter,
+            new_power: Uint128(42),
+            staked_since: Some(1_600_000_000),
+        };
+
+        assert_eq!(to_binary(&hook).unwrap(), to_binary(&factory_msg).unwrap());
+    }
+}
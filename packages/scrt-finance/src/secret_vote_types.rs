@@ -1,5 +1,7 @@
+use crate::errors::ContractError;
 use crate::types::SecretContract;
-use cosmwasm_std::{Binary, HumanAddr, Uint128};
+use crate::voting_power::VotingPowerSource;
+use cosmwasm_std::{Binary, HumanAddr, StdError, StdResult, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
@@ -11,9 +13,289 @@ pub struct PollContract {
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct PollConfig {
-    pub duration: u64,     // In seconds
-    pub quorum: u8,        // X/100% (percentage)
-    pub min_threshold: u8, // X/100% (percentage)
+    pub duration: u64,      // In seconds
+    pub quorum: u16,        // X/10000 (basis points) of the total staked
+    pub min_threshold: u16, // X/10000 (basis points) of the non-abstain votes
+    pub quorum_comparison: Comparison,
+    pub threshold_comparison: Comparison,
+    pub veto_threshold: u16, // X/10000 (basis points) of all votes. Exceeding it vetoes the poll
+    pub revote_policy: RevotePolicy,
+    pub voting_power_mode: VotingPowerMode,
+    pub power_cap: VotingPowerCap,
+    pub lock_stake: bool, // Voters can't redeem until the poll ends. Needs pool support
+}
+
+/// Whether voters can change their ballot once cast. Voting power updates don't count as re-votes
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RevotePolicy {
+    Allowed,                        // Until the poll ends
+    Disallowed,                     // The first ballot is final
+    Limited { max_revotes: u32 },   // Up to `max_revotes` times
+    UntilLock { lock_period: u64 }, // Until `lock_period` seconds before the poll ends
+}
+
+/// How a staker's balance in the staking pool translates into voting power
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingPowerMode {
+    Balance, // One staked SEFI, one vote
+    StakeAge {
+        max_multiplier: u16, // X/10000 (basis points), reached after `boost_period`
+        boost_period: u64,   // In seconds. The multiplier grows linearly with stake age until then
+    },
+}
+
+impl VotingPowerMode {
+    /// The voting power of a balance staked since `staked_since`. Stake of unknown age isn't boosted
+    pub fn voting_power(
+        &self,
+        balance: u128,
+        staked_since: Option<u64>,
+        now: u64,
+    ) -> StdResult<u128> {
+        match *self {
+            VotingPowerMode::Balance => Ok(balance),
+            VotingPowerMode::StakeAge {
+                max_multiplier,
+                boost_period,
+            } => {
+                let age = staked_since
+                    .map_or(0, |since| now.saturating_sub(since))
+                    .min(boost_period);
+                let max_boost = max_multiplier.saturating_sub(MAX_BASIS_POINTS) as u128;
+                let multiplier =
+                    MAX_BASIS_POINTS as u128 + max_boost * age as u128 / boost_period as u128;
+
+                balance
+                    .checked_mul(multiplier)
+                    .map(|power| power / MAX_BASIS_POINTS as u128)
+                    .ok_or_else(|| StdError::from(ContractError::VotingPowerOverflow))
+            }
+        }
+    }
+}
+
+/// The most voting power a single ballot can count for, so no single staker decides a poll alone
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingPowerCap {
+    Uncapped,
+    Absolute { amount: Uint128 },
+    ShareOfTotal { share: u16 }, // X/10000 (basis points) of the total staked
+}
+
+impl VotingPowerCap {
+    /// A cap must let ballots count for something
+    pub fn validate(&self) -> StdResult<()> {
+        match *self {
+            VotingPowerCap::Absolute { amount } if amount.u128() == 0 => {
+                Err(ContractError::InvalidVotingPowerCap.into())
+            }
+            VotingPowerCap::ShareOfTotal { share } if share == 0 || share > MAX_BASIS_POINTS => {
+                Err(ContractError::InvalidVotingPowerCap.into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Whether this cap lets no ballot count for more than `max` would. Absolute and share-of-total
+    /// caps can't be compared, as the total isn't known in advance
+    pub fn is_within(&self, max: &VotingPowerCap) -> bool {
+        match (*self, *max) {
+            (_, VotingPowerCap::Uncapped) => true,
+            (VotingPowerCap::Absolute { amount }, VotingPowerCap::Absolute { amount: max }) => {
+                amount <= max
+            }
+            (
+                VotingPowerCap::ShareOfTotal { share },
+                VotingPowerCap::ShareOfTotal { share: max },
+            ) => share <= max,
+            _ => false,
+        }
+    }
+
+    /// Caps voting power, given the total staked when the ballot is cast or updated
+    pub fn apply(&self, power: u128, total_staked: u128) -> StdResult<u128> {
+        match *self {
+            VotingPowerCap::Uncapped => Ok(power),
+            VotingPowerCap::Absolute { amount } => Ok(power.min(amount.u128())),
+            VotingPowerCap::ShareOfTotal { share } => total_staked
+                .checked_mul(share as u128)
+                .map(|max_power| power.min(max_power / MAX_BASIS_POINTS as u128))
+                .ok_or_else(|| StdError::from(ContractError::VotingPowerOverflow)),
+        }
+    }
+}
+
+/// Whether a quorum/threshold is met when the result is exactly equal to it
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Comparison {
+    Inclusive, // result >= required
+    Exclusive, // result > required
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Passed,
+    Rejected,
+    QuorumNotMet,
+    Tie,
+    Vetoed,
+    Cancelled, // Set by the poll author, never by the tally
+}
+
+/// Admin-configurable limits for creator-supplied `PollConfig`s
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+pub struct PollConfigBounds {
+    pub min_duration: u64,             // In seconds
+    pub max_duration: u64,             // In seconds
+    pub min_quorum: u16,               // X/10000 (basis points)
+    pub max_quorum: u16,               // X/10000 (basis points)
+    pub min_threshold: u16,            // X/10000 (basis points)
+    pub max_threshold: u16,            // X/10000 (basis points)
+    pub min_veto_threshold: u16,       // X/10000 (basis points)
+    pub max_veto_threshold: u16,       // X/10000 (basis points)
+    pub max_start_delay: u64,          // In seconds. How far ahead a poll can be scheduled
+    pub max_power_cap: VotingPowerCap, // The loosest cap a poll can set. Uncapped allows any
+}
+
+pub const MAX_BASIS_POINTS: u16 = 10_000;
+pub const MAX_POWER_MULTIPLIER: u16 = 50_000; // 5x
+
+/// Handle and query responses are padded to a multiple of this many bytes unless configured
+/// otherwise, so their encrypted size doesn't depend on their content
+pub const DEFAULT_RESPONSE_BLOCK_SIZE: u16 = 256;
+
+pub fn validate_response_block_size(block_size: u16) -> StdResult<()> {
+    if block_size == 0 {
+        return Err(ContractError::InvalidResponseBlockSize.into());
+    }
+
+    Ok(())
+}
+
+impl PollConfigBounds {
+    /// Verifies the bounds are consistent with themselves
+    pub fn validate(&self) -> StdResult<()> {
+        if self.min_duration == 0 || self.min_duration > self.max_duration {
+            return Err(ContractError::InvalidDurationBounds {
+                min: self.min_duration,
+                max: self.max_duration,
+            }
+            .into());
+        }
+        validate_percentage_bounds("quorum", self.min_quorum, self.max_quorum)?;
+        validate_percentage_bounds("threshold", self.min_threshold, self.max_threshold)?;
+        validate_percentage_bounds(
+            "veto threshold",
+            self.min_veto_threshold,
+            self.max_veto_threshold,
+        )?;
+        self.max_power_cap.validate()?;
+
+        Ok(())
+    }
+
+    /// Verifies a poll config falls within the bounds
+    pub fn check(&self, config: &PollConfig) -> StdResult<()> {
+        if config.duration < self.min_duration || config.duration > self.max_duration {
+            return Err(ContractError::DurationOutOfBounds {
+                min: self.min_duration,
+                max: self.max_duration,
+                duration: config.duration,
+            }
+            .into());
+        }
+        check_percentage("quorum", config.quorum, self.min_quorum, self.max_quorum)?;
+        check_percentage(
+            "minimum threshold",
+            config.min_threshold,
+            self.min_threshold,
+            self.max_threshold,
+        )?;
+        check_percentage(
+            "veto threshold",
+            config.veto_threshold,
+            self.min_veto_threshold,
+            self.max_veto_threshold,
+        )?;
+        if let RevotePolicy::UntilLock { lock_period } = config.revote_policy {
+            if lock_period >= config.duration {
+                return Err(ContractError::LockPeriodTooLong {
+                    lock_period,
+                    duration: config.duration,
+                }
+                .into());
+            }
+        }
+        if let VotingPowerMode::StakeAge {
+            max_multiplier,
+            boost_period,
+        } = config.voting_power_mode
+        {
+            if !(MAX_BASIS_POINTS..=MAX_POWER_MULTIPLIER).contains(&max_multiplier)
+                || boost_period == 0
+            {
+                return Err(ContractError::InvalidStakeAgeBoost {
+                    max_multiplier,
+                    boost_period,
+                }
+                .into());
+            }
+        }
+        config.power_cap.validate()?;
+        if !config.power_cap.is_within(&self.max_power_cap) {
+            return Err(ContractError::VotingPowerCapTooLoose.into());
+        }
+
+        Ok(())
+    }
+
+    /// Verifies a poll scheduled to start at `start_time` opens neither in the past nor too late
+    pub fn check_start_time(&self, start_time: u64, now: u64) -> StdResult<()> {
+        if start_time < now {
+            return Err(ContractError::StartTimeInPast.into());
+        }
+        if start_time - now > self.max_start_delay {
+            return Err(ContractError::StartTimeTooFar {
+                max_delay: self.max_start_delay,
+                delay: start_time - now,
+            }
+            .into());
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_percentage_bounds(name: &str, min: u16, max: u16) -> StdResult<()> {
+    if min > max || max > MAX_BASIS_POINTS {
+        return Err(ContractError::InvalidPercentageBounds {
+            name: name.to_string(),
+            min,
+            max,
+        }
+        .into());
+    }
+
+    Ok(())
+}
+
+fn check_percentage(name: &str, value: u16, min: u16, max: u16) -> StdResult<()> {
+    if value > MAX_BASIS_POINTS || value < min || value > max {
+        return Err(ContractError::PercentageOutOfBounds {
+            name: name.to_string(),
+            min,
+            max,
+            value,
+        }
+        .into());
+    }
+
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
@@ -25,6 +307,13 @@ pub struct PollMetadata {
     pub author_alias: String,
 }
 
+/// An eligible voter in a poll that isn't tied to a staking pool
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct PollMember {
+    pub address: HumanAddr,
+    pub weight: Option<Uint128>, // Defaults to 1, i.e. one member, one vote
+}
+
 #[derive(Serialize, Deserialize, JsonSchema, Clone)]
 pub struct RevealCommittee {
     pub n: u64,
@@ -37,7 +326,13 @@ pub struct PollInitMsg {
     pub config: PollConfig,
     pub reveal_com: RevealCommittee,
     pub choices: Vec<String>,
-    pub staking_pool: SecretContract,
+    pub voting_power_source: VotingPowerSource,
+    pub config_bounds: PollConfigBounds,
+    pub start_time: Option<u64>, // Voting opens at instantiation if not set
+    pub abstain_choice: Option<u8>, // Counts towards quorum but not towards the threshold
+    pub veto_choice: Option<u8>, // Can't win, but vetoes the poll past `veto_threshold`
+    pub private_participation: bool, // Whether someone voted is only revealed to the voter
+    pub response_block_size: u16,
     pub init_hook: Option<InitHook>,
 }
 
@@ -45,16 +340,22 @@ pub struct PollInitMsg {
 #[serde(rename_all = "snake_case")]
 pub enum PollHandleMsg {
     Vote {
-        choice: u8, // Arbitrary id that is given by the contract
-        staking_pool_viewing_key: String,
+        choice: u8,                       // Arbitrary id that is given by the contract
+        staking_pool_viewing_key: String, // In whitelist polls, sets the voter's key for this poll instead
         salt: String,
+        padding: Option<String>, // Hides the length of the salt
     },
     UpdateVotingPower {
         voter: HumanAddr,
         new_power: Uint128,
+        staked_since: Option<u64>,
     },
     Finalize {
         rolling_hash: String,
+        padding: Option<String>,
+    },
+    Cancel {
+        padding: Option<String>,
     },
 }
 
@@ -72,33 +373,118 @@ pub enum PollFactoryHandleMsg {
         poll_config: Option<PollConfig>,
         poll_choices: Vec<String>,
         pool_viewing_key: String,
+        start_time: Option<u64>, // Linux epoch. Allows a discussion period before voting opens
+        abstain_choice: Option<u8>,
+        veto_choice: Option<u8>,
+        private_participation: Option<bool>, // Defaults to false
+        padding: Option<String>,
+    },
+    /// A poll only the listed members can vote in, regardless of their stake. Council only
+    NewMemberPoll {
+        poll_metadata: PollMetadata,
+        poll_config: Option<PollConfig>,
+        poll_choices: Vec<String>,
+        members: Vec<PollMember>,
+        start_time: Option<u64>,
+        abstain_choice: Option<u8>,
+        veto_choice: Option<u8>,
+        private_participation: Option<bool>,
+        padding: Option<String>,
     },
 
     // Staking contract callback
     UpdateVotingPower {
         voter: HumanAddr,
         new_power: Uint128,
+        staked_since: Option<u64>,
     },
 
-    // Poll contract callback
+    // Poll contract callbacks
     RegisterForUpdates {
         challenge: String,
+        start_time: u64,
         end_time: u64,
     },
+    ReportResult {
+        outcome: Outcome,
+        winning_choice: Option<u8>,
+    },
+    LockStake {
+        voter: HumanAddr,
+    },
 
     // Admin
     UpdateDefaultPollConfig {
-        duration: Option<u64>,     // In seconds
-        quorum: Option<u8>,        // X/100% (percentage)
-        min_threshold: Option<u8>, // X/100% (percentage)
+        duration: Option<u64>,      // In seconds
+        quorum: Option<u16>,        // X/10000 (basis points)
+        min_threshold: Option<u16>, // X/10000 (basis points)
+        quorum_comparison: Option<Comparison>,
+        threshold_comparison: Option<Comparison>,
+        veto_threshold: Option<u16>, // X/10000 (basis points)
+        revote_policy: Option<RevotePolicy>,
+        voting_power_mode: Option<VotingPowerMode>,
+        power_cap: Option<VotingPowerCap>,
+        lock_stake: Option<bool>,
+    },
+    UpdatePollConfigBounds {
+        bounds: PollConfigBounds,
     },
     UpdateConfig {
         new_poll_code: Option<PollContract>,
         new_staking_pool: Option<SecretContract>,
         new_min_stake_amount: Option<Uint128>,
         new_reveal_com: Option<RevealCommittee>,
+        new_response_block_size: Option<u16>,
+        new_council: Option<HumanAddr>,
     },
     ChangeAdmin {
         new_admin: HumanAddr,
     },
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stake_age_voting_power() {
+        let mode = VotingPowerMode::StakeAge {
+            max_multiplier: 20_000,
+            boost_period: 1000,
+        };
+        assert_eq!(mode.voting_power(100, Some(5000), 5000).unwrap(), 100);
+        assert_eq!(mode.voting_power(100, Some(4500), 5000).unwrap(), 150);
+        assert_eq!(mode.voting_power(100, Some(4000), 5000).unwrap(), 200);
+        assert_eq!(mode.voting_power(100, Some(0), 5000).unwrap(), 200); // Capped
+        assert_eq!(mode.voting_power(100, None, 5000).unwrap(), 100);
+        assert_eq!(
+            VotingPowerMode::Balance
+                .voting_power(100, Some(0), 5000)
+                .unwrap(),
+            100
+        );
+        assert_eq!(
+            mode.voting_power(u128::MAX, Some(0), 5000).unwrap_err(),
+            StdError::from(ContractError::VotingPowerOverflow)
+        );
+    }
+
+    #[test]
+    fn voting_power_cap() {
+        assert_eq!(VotingPowerCap::Uncapped.apply(500, 1000).unwrap(), 500);
+
+        let cap = VotingPowerCap::Absolute {
+            amount: Uint128(300),
+        };
+        assert_eq!(cap.apply(500, 1000).unwrap(), 300);
+        assert_eq!(cap.apply(200, 1000).unwrap(), 200);
+
+        let cap = VotingPowerCap::ShareOfTotal { share: 2500 };
+        assert_eq!(cap.apply(500, 1000).unwrap(), 250);
+        assert_eq!(cap.apply(200, 1000).unwrap(), 200);
+        assert_eq!(
+            cap.apply(1, u128::MAX).unwrap_err(),
+            StdError::from(ContractError::VotingPowerOverflow)
+        );
+    }
+}
@@ -0,0 +1,9 @@
+use cosmwasm_std::HumanAddr;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+pub struct SecretContract {
+    pub address: HumanAddr,
+    pub contract_hash: String,
+}
@@ -0,0 +1,185 @@
+//! Where a poll's voting power comes from.
+//!
+//! Every source answers the same two questions: how much power a voter has, and how much power
+//! there is in total (the quorum's denominator). Polls only talk to sources through these, so a new
+//! kind of source doesn't touch the tally.
+//!
+//! A source must keep a voter's power from counting twice: a balance that can be transferred after
+//! voting has to be locked, or pushed to the poll when it changes. Plain SNIP-20 and SNIP-721
+//! balances can do neither, so tokens have to be staked or locked in escrow to vote with.
+use crate::errors::ContractError;
+use crate::lp_staking_msg::{LPStakingQueryAnswer, LPStakingQueryMsg};
+use crate::secret_vote_types::PollMember;
+use crate::types::SecretContract;
+use cosmwasm_std::{to_binary, HumanAddr, Querier, QueryRequest, StdError, StdResult, WasmQuery};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum VotingPowerSource {
+    /// Balances staked in a pool, out of its total locked. The pool pushes balance changes
+    StakingPool { contract: SecretContract },
+    /// A fixed list of members, out of their total weight. Kept private
+    Whitelist { members: Vec<PollMember> },
+}
+
+impl VotingPowerSource {
+    /// Validates a whitelist. Contracts can only be checked by querying them
+    pub fn check(&self) -> StdResult<()> {
+        if let VotingPowerSource::Whitelist { members } = self {
+            if members.is_empty() {
+                return Err(ContractError::InvalidMemberList.into());
+            }
+            for (i, member) in members.iter().enumerate() {
+                let duplicate = members[..i].iter().any(|m| m.address == member.address);
+                if member_weight(member) == 0 || duplicate {
+                    return Err(ContractError::InvalidMemberList.into());
+                }
+            }
+            total_weight(members)?;
+        }
+
+        Ok(())
+    }
+
+    /// Whether balance lookups verify the voter's viewing key. Whitelists have no keys
+    pub fn authenticates(&self) -> bool {
+        !matches!(self, VotingPowerSource::Whitelist { .. })
+    }
+
+    /// The pool that pushes voting power updates, if any
+    pub fn staking_pool(&self) -> Option<&SecretContract> {
+        match self {
+            VotingPowerSource::StakingPool { contract } => Some(contract),
+            _ => None,
+        }
+    }
+
+    /// The voter's voting power, read with their viewing key where the source has keys. `as_of` is
+    /// passed on to staking pools, whose power may decay with time
+    pub fn balance<Q: Querier>(
+        &self,
+        querier: &Q,
+        address: HumanAddr,
+        key: String,
+        as_of: Option<u64>,
+    ) -> StdResult<u128> {
+        match self {
+            VotingPowerSource::StakingPool { contract } => match query(
+                querier,
+                contract,
+                &LPStakingQueryMsg::Balance {
+                    address,
+                    key,
+                    as_of,
+                },
+            )? {
+                LPStakingQueryAnswer::Balance { amount } => Ok(amount.u128()),
+                LPStakingQueryAnswer::ViewingKeyError { .. } => Err(StdError::unauthorized()),
+                _ => Err(ContractError::UnexpectedStakingPoolAnswer.into()),
+            },
+            VotingPowerSource::Whitelist { members } => members
+                .iter()
+                .find(|member| member.address == address)
+                .map(member_weight)
+                .ok_or_else(|| StdError::from(ContractError::NotAMember)),
+        }
+    }
+
+    pub fn total_supply<Q: Querier>(&self, querier: &Q, as_of: Option<u64>) -> StdResult<u128> {
+        match self {
+            VotingPowerSource::StakingPool { contract } => {
+                match query(querier, contract, &LPStakingQueryMsg::TotalLocked { as_of })? {
+                    LPStakingQueryAnswer::TotalLocked { amount } => Ok(amount.u128()),
+                    _ => Err(ContractError::UnexpectedStakingPoolAnswer.into()),
+                }
+            }
+            VotingPowerSource::Whitelist { members } => total_weight(members),
+        }
+    }
+}
+
+fn member_weight(member: &PollMember) -> u128 {
+    member.weight.map_or(1, |weight| weight.u128())
+}
+
+fn total_weight(members: &[PollMember]) -> StdResult<u128> {
+    members
+        .iter()
+        .try_fold(0u128, |total, member| {
+            total.checked_add(member_weight(member))
+        })
+        .ok_or_else(|| StdError::from(ContractError::TallyOverflow))
+}
+
+fn query<Q: Querier, M: Serialize, T: serde::de::DeserializeOwned>(
+    querier: &Q,
+    contract: &SecretContract,
+    msg: &M,
+) -> StdResult<T> {
+    querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+        callback_code_hash: contract.contract_hash.clone(),
+        contract_addr: contract.address.clone(),
+        msg: to_binary(msg)?,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::mock_dependencies;
+    use cosmwasm_std::Uint128;
+
+    #[test]
+    fn whitelist_voting_power() {
+        let member = |address: &str, weight: Option<u128>| PollMember {
+            address: HumanAddr(address.to_string()),
+            weight: weight.map(Uint128),
+        };
+        let querier = mock_dependencies(20, &[]).querier;
+
+        let source = VotingPowerSource::Whitelist {
+            members: vec![member("alice", None), member("bob", Some(3))],
+        };
+        source.check().unwrap();
+        assert!(!source.authenticates());
+        let balance = |address: &str| {
+            source.balance(
+                &querier,
+                HumanAddr(address.to_string()),
+                "key".to_string(),
+                None,
+            )
+        };
+        assert_eq!(balance("alice").unwrap(), 1);
+        assert_eq!(balance("bob").unwrap(), 3);
+        assert_eq!(
+            balance("eve").unwrap_err(),
+            StdError::from(ContractError::NotAMember)
+        );
+        assert_eq!(source.total_supply(&querier, None).unwrap(), 4);
+
+        for members in &[
+            vec![],
+            vec![member("alice", None), member("alice", Some(2))],
+            vec![member("alice", Some(0))],
+        ] {
+            assert_eq!(
+                VotingPowerSource::Whitelist {
+                    members: members.clone()
+                }
+                .check()
+                .unwrap_err(),
+                StdError::from(ContractError::InvalidMemberList)
+            );
+        }
+        let overflowing = VotingPowerSource::Whitelist {
+            members: vec![member("alice", Some(u128::MAX)), member("bob", None)],
+        };
+        assert_eq!(
+            overflowing.check().unwrap_err(),
+            StdError::from(ContractError::TallyOverflow)
+        );
+    }
+}
@@ -0,0 +1,130 @@
+//! Lets a reveal committee member verify a poll's rolling hash independently before finalizing.
+//!
+//! Usage: reveal-check <transcript.json> <rolling hash>
+//!
+//! The transcript holds the votes the member collected, in the order they were cast:
+//! ```text
+//! {"votes": [{"voter": "secret1...", "choice": 0, "voting_power": "100", "salt": "..."}]}
+//! ```
+//! The rolling hash is either the hex string or the raw answer to the poll's `rolling_hash` query.
+//! On a match, prints the `finalize` message to sign.
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
+use serde::Deserialize;
+use std::{env, fs, process};
+use voting_client::hash::{rolling_hash, HashedVote};
+use voting_client::poll::{HandleMsg, RollingHash};
+use voting_client::TypedQuery;
+
+const USAGE: &str = "usage: reveal-check <transcript.json> <rolling hash>";
+
+#[derive(Deserialize)]
+struct Transcript {
+    votes: Vec<TranscriptVote>,
+}
+
+#[derive(Deserialize)]
+struct TranscriptVote {
+    voter: HumanAddr,
+    choice: u8,
+    voting_power: Uint128,
+    salt: String,
+}
+
+fn main() {
+    if let Err(e) = run(env::args().skip(1).collect()) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+}
+
+fn run(args: Vec<String>) -> Result<(), String> {
+    if args.len() != 2 {
+        return Err(USAGE.to_string());
+    }
+
+    let transcript = fs::read(&args[0]).map_err(|e| format!("can't read {}: {}", args[0], e))?;
+    println!("{}", check(&transcript, &args[1])?);
+
+    Ok(())
+}
+
+/// The `finalize` message to sign, if the transcript reproduces the rolling hash
+fn check(transcript: &[u8], rolling_hash_arg: &str) -> Result<String, String> {
+    let transcript: Transcript =
+        serde_json::from_slice(transcript).map_err(|e| format!("invalid transcript: {}", e))?;
+    let votes: Vec<HashedVote> = transcript
+        .votes
+        .into_iter()
+        .map(|v| HashedVote {
+            voter: v.voter,
+            choice: v.choice,
+            voting_power: v.voting_power.u128(),
+            salt: v.salt,
+        })
+        .collect();
+
+    let expected = parse_rolling_hash(rolling_hash_arg)?;
+    let computed = rolling_hash(&votes);
+    if computed != expected {
+        return Err(format!(
+            "rolling hash mismatch: the transcript gives {}, but the poll reports {}",
+            computed, expected
+        ));
+    }
+
+    let finalize = HandleMsg::Finalize {
+        rolling_hash: computed,
+        padding: None,
+    };
+    serde_json::to_string(&finalize).map_err(|e| e.to_string())
+}
+
+fn parse_rolling_hash(arg: &str) -> Result<String, String> {
+    let arg = arg.trim();
+    if arg.starts_with('{') {
+        return RollingHash::parse(&Binary::from(arg.as_bytes()))
+            .map_err(|e| format!("invalid rolling hash answer: {}", e));
+    }
+
+    Ok(arg.to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TRANSCRIPT: &str = r#"{"votes": [
+        {"voter": "alice", "choice": 0, "voting_power": "100", "salt": "salt a"},
+        {"voter": "bob", "choice": 1, "voting_power": "200", "salt": "salt b"}
+    ]}"#;
+    const HASH: &str = "53e92e19156fb9a28d798ba76153aadd737f28a14fd3ae209c33780bb6078bd5";
+
+    #[test]
+    fn test_check() {
+        let finalize = check(TRANSCRIPT.as_bytes(), HASH).unwrap();
+        assert_eq!(
+            finalize,
+            format!(
+                r#"{{"finalize":{{"rolling_hash":"{}","padding":null}}}}"#,
+                HASH
+            )
+        );
+        let answer = format!(r#"{{"rolling_hash":{{"hash":"{}"}}}}"#, HASH);
+        assert_eq!(check(TRANSCRIPT.as_bytes(), &answer).unwrap(), finalize);
+
+        // Tampering with any vote, not just the last one, changes the hash
+        let tampered = [
+            TRANSCRIPT.replace(r#""100""#, r#""101""#),
+            TRANSCRIPT.replace("salt a", "salt c"),
+            TRANSCRIPT.replace("alice", "eve"),
+        ];
+        for transcript in &tampered {
+            let err = check(transcript.as_bytes(), HASH).unwrap_err();
+            assert!(err.starts_with("rolling hash mismatch"));
+        }
+        let dropped = r#"{"votes": [
+            {"voter": "bob", "choice": 1, "voting_power": "200", "salt": "salt b"}
+        ]}"#;
+        assert!(check(dropped.as_bytes(), HASH).is_err());
+    }
+}
@@ -0,0 +1,24 @@
+//! Poll factory messages and queries
+pub use poll_factory::msg::{InitMsg, QueryAnswer, QueryMsg, ResponseStatus};
+pub use poll_factory::state::{ActivePoll, PollRecord};
+pub use scrt_finance::secret_vote_types::PollFactoryHandleMsg as HandleMsg;
+
+use cosmwasm_std::{HumanAddr, Uint128};
+use scrt_finance::secret_vote_types::{self as types, PollConfig};
+use scrt_finance::types::SecretContract;
+
+typed_queries! {
+    QueryMsg => QueryAnswer;
+
+    ActivePolls { current_time: u64 } -> Vec<ActivePoll> =
+        ActivePolls { active_polls } => active_polls;
+    DefaultPollConfig {} -> PollConfig = DefaultPollConfig { poll_config } => poll_config;
+    PollConfigBounds {} -> types::PollConfigBounds = PollConfigBounds { bounds } => bounds;
+    StakingPool {} -> SecretContract = StakingPool { contract } => contract;
+    PollCode {} -> types::PollContract = PollCode { contract } => contract;
+    Admin {} -> HumanAddr = Admin { address } => address;
+    RevealCommittee {} -> types::RevealCommittee = RevealCommittee { committee } => committee;
+    MinimumStake {} -> Uint128 = MinimumStake { amount } => amount;
+    Council {} -> Option<HumanAddr> = Council { address } => address;
+    Poll { address: HumanAddr } -> PollRecord = Poll { poll } => poll;
+}
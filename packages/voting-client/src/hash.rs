@@ -0,0 +1,37 @@
+//! Reproduces a poll's rolling hash off-chain, e.g. for the reveal committee to verify the votes
+//! it saw before calling `Finalize`
+use cosmwasm_std::HumanAddr;
+pub use secret_poll::contract::roll_hash;
+use secret_poll::state::Vote;
+use sha2::{Digest, Sha256};
+
+/// A `Vote` message as the poll received it. Voting power updates don't affect the hash
+pub struct HashedVote {
+    pub voter: HumanAddr,
+    pub choice: u8,
+    pub voting_power: u128,
+    pub salt: String,
+}
+
+/// The rolling hash after `votes`, in the order they were cast, formatted the way `Finalize`
+/// expects it
+pub fn rolling_hash(votes: &[HashedVote]) -> String {
+    let hash = votes.iter().fold([0u8; 32], |hash, vote| {
+        roll_hash(
+            hash,
+            &vote.voter,
+            Vote {
+                choice: vote.choice,
+                voting_power: vote.voting_power,
+            },
+            vote.salt.clone(),
+        )
+    });
+
+    hex::encode(hash)
+}
+
+/// Derives a salt for `HandleMsg::Vote` from the caller's entropy
+pub fn salt(entropy: &[u8]) -> String {
+    hex::encode(Sha256::digest(entropy))
+}
@@ -0,0 +1,150 @@
+//! A typed client for the poll factory and secret poll contracts.
+//!
+//! Messages are re-exported from the contract crates so they can't drift, and every query is a
+//! struct that knows its own answer type, e.g. `factory::ActivePolls { current_time }` parses into
+//! a `Vec<ActivePoll>`.
+use cosmwasm_std::{
+    to_binary, to_vec, Binary, CosmosMsg, Empty, Querier, QueryRequest, StdError, StdResult,
+    WasmMsg, WasmQuery,
+};
+use scrt_finance::types::SecretContract;
+use serde::Serialize;
+
+macro_rules! typed_queries {
+    ($msg:ident => $answer:ident; $(
+        $(#[$meta:meta])*
+        $name:ident { $($field:ident: $fty:ty),* } -> $out:ty =
+            $variant:ident { $($bind:ident),* } => $expr:expr
+    );* $(;)?) => {
+        $(
+            $(#[$meta])*
+            pub struct $name {
+                $(pub $field: $fty),*
+            }
+
+            impl $crate::TypedQuery for $name {
+                type Msg = $msg;
+                type Answer = $out;
+
+                fn msg(self) -> $msg {
+                    $msg::$name { $($field: self.$field),* }
+                }
+
+                fn parse(answer: &cosmwasm_std::Binary) -> cosmwasm_std::StdResult<$out> {
+                    match cosmwasm_std::from_binary(answer)? {
+                        $answer::$variant { $($bind,)* .. } => Ok($expr),
+                        _ => Err($crate::unexpected_answer(stringify!($name))),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+pub mod factory;
+pub mod hash;
+pub mod poll;
+
+/// A query paired with the answer it expects
+pub trait TypedQuery {
+    type Msg: Serialize;
+    type Answer;
+
+    fn msg(self) -> Self::Msg;
+    fn parse(answer: &Binary) -> StdResult<Self::Answer>;
+}
+
+/// The JSON to send as the query, for off-chain callers
+pub fn query_msg<T: TypedQuery>(query: T) -> StdResult<Binary> {
+    to_binary(&query.msg())
+}
+
+/// Runs a typed query through `querier`, e.g. from another contract
+pub fn query<Q: Querier, T: TypedQuery>(
+    querier: &Q,
+    contract: &SecretContract,
+    query: T,
+) -> StdResult<T::Answer> {
+    let request: QueryRequest<Empty> = QueryRequest::Wasm(WasmQuery::Smart {
+        contract_addr: contract.address.clone(),
+        callback_code_hash: contract.contract_hash.clone(),
+        msg: query_msg(query)?,
+    });
+
+    match querier.raw_query(&to_vec(&request)?) {
+        Ok(Ok(answer)) => T::parse(&answer),
+        Ok(Err(e)) => Err(e),
+        Err(e) => Err(StdError::generic_err(format!(
+            "querier system error: {:?}",
+            e
+        ))),
+    }
+}
+
+/// Wraps a handle message for `contract` in a `CosmosMsg`
+pub fn execute_msg<T: Serialize>(contract: &SecretContract, msg: &T) -> StdResult<CosmosMsg> {
+    Ok(CosmosMsg::Wasm(WasmMsg::Execute {
+        contract_addr: contract.address.clone(),
+        callback_code_hash: contract.contract_hash.clone(),
+        msg: to_binary(msg)?,
+        send: vec![],
+    }))
+}
+
+fn unexpected_answer(query: &str) -> StdError {
+    StdError::generic_err(format!("unexpected answer to a {} query", query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{HumanAddr, Uint128};
+
+    #[test]
+    fn test_typed_query() {
+        let msg = query_msg(factory::Poll {
+            address: HumanAddr("poll".to_string()),
+        })
+        .unwrap();
+        assert_eq!(
+            msg,
+            Binary::from(br#"{"poll":{"address":"poll"}}"#.to_vec())
+        );
+
+        let answer = to_binary(&factory::QueryAnswer::MinimumStake {
+            amount: Uint128(1_000_000),
+        })
+        .unwrap();
+        assert_eq!(
+            factory::MinimumStake::parse(&answer).unwrap(),
+            Uint128(1_000_000)
+        );
+        assert!(factory::Admin::parse(&answer).is_err());
+    }
+
+    #[test]
+    fn test_rolling_hash() {
+        assert_eq!(hash::rolling_hash(&[]), "00".repeat(32));
+        assert_eq!(hash::salt(b"entropy").len(), 64);
+
+        let vote = |voter: &str, choice: u8, voting_power: u128, salt: &str| hash::HashedVote {
+            voter: HumanAddr(voter.to_string()),
+            choice,
+            voting_power,
+            salt: salt.to_string(),
+        };
+        let a = || vote("alice", 0, 100, "salt a");
+        let b = || vote("bob", 1, 200, "salt b");
+        assert_eq!(
+            hash::rolling_hash(&[a(), b()]),
+            "53e92e19156fb9a28d798ba76153aadd737f28a14fd3ae209c33780bb6078bd5"
+        );
+
+        // Every vote is chained in, not just the last one
+        assert_ne!(hash::rolling_hash(&[a(), b()]), hash::rolling_hash(&[b()]));
+        assert_ne!(
+            hash::rolling_hash(&[a(), b()]),
+            hash::rolling_hash(&[b(), a()])
+        );
+    }
+}
@@ -0,0 +1,73 @@
+//! Secret poll messages and queries
+pub use scrt_finance::secret_vote_types::{PollHandleMsg as HandleMsg, PollInitMsg as InitMsg};
+pub use secret_poll::msg::{FinalizeAnswer, PollPhase, QueryAnswer, QueryMsg, ResponseStatus};
+pub use secret_poll::state::{PollResult, StoredPollConfig};
+
+use cosmwasm_std::{from_binary, Binary, HumanAddr, StdResult, Uint128};
+use scrt_finance::secret_vote_types::{self as types, PollMetadata};
+
+pub struct VoteInfoAnswer {
+    pub metadata: PollMetadata,
+    pub config: StoredPollConfig,
+    pub reveal_com: types::RevealCommittee,
+    pub phase: Option<PollPhase>,
+}
+
+pub struct TallyAnswer {
+    pub choices: Vec<String>,
+    pub tally: Vec<Uint128>,
+}
+
+pub struct ResultAnswer {
+    pub choices: Vec<String>,
+    pub result: PollResult,
+}
+
+pub struct VoteAnswer {
+    pub choice: u8,
+    pub voting_power: Uint128,
+    pub uncapped_voting_power: Uint128,
+}
+
+pub struct RevealedAnswer {
+    pub required: u64,
+    pub num_revealed: u64,
+    pub revealed: Vec<HumanAddr>,
+}
+
+typed_queries! {
+    QueryMsg => QueryAnswer;
+
+    Choices {} -> Vec<String> = Choices { choices } => choices;
+    VoteInfo { current_time: Option<u64> } -> VoteInfoAnswer =
+        VoteInfo { metadata, config, reveal_com, phase } => VoteInfoAnswer {
+            metadata,
+            config,
+            reveal_com,
+            phase,
+        };
+    HasVoted { voter: HumanAddr, key: Option<String> } -> bool = HasVoted { has_voted } => has_voted;
+    Tally {} -> TallyAnswer = Tally { choices, tally } => TallyAnswer { choices, tally };
+    Result {} -> ResultAnswer = Result { choices, result } => ResultAnswer { choices, result };
+    NumberOfVoters {} -> u64 = NumberOfVoters { count } => count;
+    RevealCommittee {} -> types::RevealCommittee = RevealCommittee { committee } => committee;
+    Revealed {} -> RevealedAnswer =
+        Revealed { required, num_revealed, revealed } => RevealedAnswer {
+            required,
+            num_revealed,
+            revealed,
+        };
+    RollingHash {} -> String = RollingHash { hash } => hash;
+    /// Authenticated with the voter's staking pool viewing key
+    Vote { voter: HumanAddr, key: String } -> VoteAnswer =
+        Vote { choice, voting_power, uncapped_voting_power } => VoteAnswer {
+            choice,
+            voting_power,
+            uncapped_voting_power,
+        };
+}
+
+/// Parses the data returned by `HandleMsg::Finalize`
+pub fn parse_finalize(data: &Binary) -> StdResult<FinalizeAnswer> {
+    from_binary(data)
+}